@@ -0,0 +1,100 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use gumbel_top_bucket::{draw_order_key, GumbelTopBucket};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+fn scores(n: usize) -> Vec<f64> {
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..n).map(|_| rng.gen()).collect()
+}
+
+fn construction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("construction");
+    for n in [10, 1_000] {
+        let scores = scores(n);
+        group.bench_with_input(BenchmarkId::new("new", n), &scores, |b, scores| {
+            b.iter(|| GumbelTopBucket::new(black_box(scores), 1.0))
+        });
+        group.bench_with_input(BenchmarkId::new("new_with_rng", n), &scores, |b, scores| {
+            let mut rng = StdRng::seed_from_u64(0);
+            b.iter(|| GumbelTopBucket::new_with_rng(black_box(scores), 1.0, &mut rng))
+        });
+        group.bench_with_input(BenchmarkId::new("new_fast", n), &scores, |b, scores| {
+            b.iter(|| GumbelTopBucket::new_fast(black_box(scores), 1.0))
+        });
+    }
+    group.finish();
+}
+
+fn small_construction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("small_construction");
+    for n in [1, 2, 3] {
+        let scores = scores(n);
+        group.bench_with_input(BenchmarkId::new("new_with_rng", n), &scores, |b, scores| {
+            let mut rng = StdRng::seed_from_u64(0);
+            b.iter(|| GumbelTopBucket::new_with_rng(black_box(scores), 1.0, &mut rng))
+        });
+        group.bench_with_input(BenchmarkId::new("reset_noise", n), &scores, |b, scores| {
+            let mut rng = StdRng::seed_from_u64(0);
+            let mut bucket = GumbelTopBucket::new_with_rng(scores, 1.0, &mut rng);
+            b.iter(|| bucket.reset_noise_with_rng(black_box(1.0), &mut rng))
+        });
+    }
+    // the sort on its own, with and without the special case for tiny slices.
+    for n in [1, 2] {
+        let noisy_scores: Vec<(usize, f64)> = scores(n).into_iter().enumerate().collect();
+        group.bench_with_input(
+            BenchmarkId::new("sort_fast_path", n),
+            &noisy_scores,
+            |b, s| {
+                b.iter_batched_ref(
+                    || s.clone(),
+                    |s| GumbelTopBucket::sort_noisy_scores(black_box(s)),
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("sort_general", n),
+            &noisy_scores,
+            |b, s| {
+                b.iter_batched_ref(
+                    || s.clone(),
+                    |s| {
+                        black_box(s).sort_unstable_by_key(|&(idx, noisy_score)| {
+                            (draw_order_key(noisy_score), idx)
+                        })
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+#[cfg(feature = "rayon")]
+fn parallel_construction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parallel_construction");
+    group.sample_size(10);
+    let n = 1_000_000;
+    let scores = scores(n);
+    group.bench_with_input(BenchmarkId::new("new", n), &scores, |b, scores| {
+        b.iter(|| GumbelTopBucket::new(black_box(scores), 1.0))
+    });
+    group.bench_with_input(BenchmarkId::new("new_par", n), &scores, |b, scores| {
+        b.iter(|| GumbelTopBucket::new_par(black_box(scores), 1.0))
+    });
+    group.finish();
+}
+
+#[cfg(feature = "rayon")]
+criterion_group!(
+    benches,
+    construction,
+    small_construction,
+    parallel_construction
+);
+#[cfg(not(feature = "rayon"))]
+criterion_group!(benches, construction, small_construction);
+criterion_main!(benches);
@@ -0,0 +1,83 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use gumbel_top_bucket::GumbelTopBucket;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const SIZES: [usize; 3] = [100, 10_000, 1_000_000];
+
+fn scores(n: usize) -> Vec<f64> {
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..n).map(|_| rng.gen()).collect()
+}
+
+/// Drains sorted noisy scores the way the bucket used to, by removing the front of the vector
+/// on every draw. Each removal shifts all the remaining entries, so a full drain is quadratic.
+fn drain_remove_first(mut noisy_scores: Vec<(usize, f64)>) -> usize {
+    let mut drawn = 0;
+    while !noisy_scores.is_empty() {
+        black_box(noisy_scores.remove(0));
+        drawn += 1;
+    }
+    drawn
+}
+
+fn drain(c: &mut Criterion) {
+    let mut group = c.benchmark_group("drain");
+    group.sample_size(10);
+    for n in SIZES {
+        let bucket = GumbelTopBucket::new(&scores(n), 1.0);
+        group.bench_with_input(BenchmarkId::new("cursor", n), &bucket, |b, bucket| {
+            b.iter_batched(
+                || bucket.clone(),
+                |mut bucket| bucket.drain().count(),
+                BatchSize::LargeInput,
+            )
+        });
+        // a single quadratic drain of a million scores takes minutes, so it is left out.
+        if n <= 10_000 {
+            let noisy_scores = bucket.clone().drain_with_scores().collect::<Vec<_>>();
+            group.bench_with_input(
+                BenchmarkId::new("remove_first", n),
+                &noisy_scores,
+                |b, noisy_scores| {
+                    b.iter_batched(
+                        || noisy_scores.clone(),
+                        drain_remove_first,
+                        BatchSize::LargeInput,
+                    )
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+fn single_draw(c: &mut Criterion) {
+    let mut group = c.benchmark_group("single_draw");
+    for n in SIZES {
+        let bucket = GumbelTopBucket::new(&scores(n), 1.0);
+        group.bench_with_input(BenchmarkId::new("draw", n), &bucket, |b, bucket| {
+            b.iter_batched_ref(
+                || bucket.clone(),
+                |bucket| bucket.draw(),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn construction_by_size(c: &mut Criterion) {
+    let mut group = c.benchmark_group("construction_by_size");
+    group.sample_size(10);
+    for n in SIZES {
+        let scores = scores(n);
+        group.bench_with_input(BenchmarkId::new("new", n), &scores, |b, scores| {
+            b.iter(|| GumbelTopBucket::new(black_box(scores), 1.0))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, drain, single_draw, construction_by_size);
+criterion_main!(benches);
@@ -0,0 +1,29 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use gumbel_top_bucket::sample_tokens_with_rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// The vocabulary size of GPT-2.
+const VOCAB_SIZE: usize = 50257;
+
+fn logits() -> Vec<f32> {
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..VOCAB_SIZE)
+        .map(|_| rng.gen_range(-10.0..10.0))
+        .collect()
+}
+
+fn sample_tokens(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sample_tokens");
+    let logits = logits();
+    for top_k in [1, 50, 1_000] {
+        group.bench_with_input(BenchmarkId::new("top_k", top_k), &top_k, |b, &top_k| {
+            let mut rng = StdRng::seed_from_u64(0);
+            b.iter(|| sample_tokens_with_rng(black_box(&logits), 0.8, top_k, &mut rng))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, sample_tokens);
+criterion_main!(benches);
@@ -0,0 +1,63 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use gumbel_top_bucket::draw_order_key;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::Ordering;
+
+fn noisy_scores(n: usize) -> Vec<(usize, f64)> {
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..n)
+        .map(|i| (i, rng.gen::<f64>() - (-rng.gen::<f64>().ln()).ln()))
+        .collect()
+}
+
+/// Sorts the noisy scores the way the bucket used to, with a comparator that checks for NaN
+/// and compares the floats with `partial_cmp` on every comparison.
+fn sort_by_partial_cmp(noisy_scores: &mut [(usize, f64)]) {
+    noisy_scores.sort_unstable_by(|a, b| {
+        let by_score = match (a.1.is_nan(), b.1.is_nan()) {
+            (false, false) => b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal),
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (true, true) => Ordering::Equal,
+        };
+        by_score.then(a.0.cmp(&b.0))
+    });
+}
+
+/// Sorts the noisy scores the way the bucket does now, by the integer key that maps the
+/// floats to their draw order; see `sort_noisy_scores`.
+fn sort_by_key(noisy_scores: &mut [(usize, f64)]) {
+    noisy_scores.sort_unstable_by_key(|&(idx, noisy_score)| (draw_order_key(noisy_score), idx));
+}
+
+fn sort_comparator(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sort_comparator");
+    group.sample_size(10);
+    for n in [1_000, 1_000_000] {
+        let noisy_scores = noisy_scores(n);
+        for (name, sort) in [
+            (
+                "partial_cmp",
+                sort_by_partial_cmp as fn(&mut [(usize, f64)]),
+            ),
+            ("key", sort_by_key),
+        ] {
+            group.bench_with_input(
+                BenchmarkId::new(name, n),
+                &noisy_scores,
+                |b, noisy_scores| {
+                    b.iter_batched_ref(
+                        || noisy_scores.clone(),
+                        |noisy_scores| sort(noisy_scores),
+                        BatchSize::LargeInput,
+                    )
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, sort_comparator);
+criterion_main!(benches);
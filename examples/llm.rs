@@ -0,0 +1,22 @@
+//! Samples tokens from made-up language-model logits with `sample_tokens`, as a replacement
+//! for top-k sampling. Run with `cargo run --example llm --features llm`.
+
+use gumbel_top_bucket::sample_tokens;
+
+fn main() {
+    let vocab = ["the", "a", "cat", "dog", "sat", "<pad>", "ran", "on"];
+    let mut logits = [2.0f32, 1.5, 0.5, 0.4, -1.0, 0.0, -0.5, 0.1];
+    // mask the padding token, so that it is never sampled.
+    logits[5] = f32::NEG_INFINITY;
+
+    // the first token is a regular sample at the given temperature.
+    let next = sample_tokens(&logits, 0.7, 1)[0];
+    println!("next token: {}", vocab[next]);
+
+    // the top 3 is a sample of three distinct tokens, e.g. for beam candidates.
+    let candidates: Vec<&str> = sample_tokens(&logits, 0.7, 3)
+        .into_iter()
+        .map(|token| vocab[token])
+        .collect();
+    println!("candidates: {:?}", candidates);
+}
@@ -0,0 +1,23 @@
+//! Builds two buckets from the same seed, drains them, and checks that they yield the same
+//! permutation. Run with `cargo run --example reproducible`.
+
+use gumbel_top_bucket::GumbelTopBucket;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+const SEED: u64 = 42;
+
+fn permutation(scores: &[f64]) -> Vec<usize> {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let mut bucket = GumbelTopBucket::new_with_rng(scores, 1.0, &mut rng);
+    bucket.drain().collect()
+}
+
+fn main() {
+    let scores = [0.1, 0.5, 0.2, 0.9, 0.3, 0.7, 0.4, 0.6];
+    let first = permutation(&scores);
+    println!("first run:  {:?}", first);
+    let second = permutation(&scores);
+    println!("second run: {:?}", second);
+    assert_eq!(first, second, "two runs with seed {} differ", SEED);
+}
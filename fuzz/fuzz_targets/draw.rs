@@ -0,0 +1,22 @@
+//! Builds a bucket from arbitrary scores and temperatures, including NaN, infinities and
+//! subnormals, and checks that draining it yields every index exactly once.
+#![no_main]
+
+use gumbel_top_bucket::GumbelTopBucket;
+use libfuzzer_sys::fuzz_target;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+fuzz_target!(|input: (f64, u64, Vec<f64>)| {
+    let (temperature, seed, scores) = input;
+    // `new` panics on these by design, so they are not interesting here.
+    if scores.is_empty() || !(temperature.is_finite() && temperature >= 0.0) {
+        return;
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut bucket = GumbelTopBucket::new_with_rng(&scores, temperature, &mut rng);
+    let mut drawn: Vec<usize> = bucket.drain().collect();
+    assert!(bucket.is_empty());
+    drawn.sort_unstable();
+    assert_eq!(drawn, (0..scores.len()).collect::<Vec<_>>());
+});
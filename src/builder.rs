@@ -0,0 +1,121 @@
+use core::iter;
+
+use num_traits::Float;
+use rand::Rng;
+
+use crate::{FloatAdd, Gumbel, GumbelTopBucket, NoiseSource};
+
+/// Marks a `GumbelBucketBuilder` that draws its noise from the thread-local random number
+/// generator, as no other generator was given to it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThreadLocalRng;
+
+/// A builder for a GumbelTopBucket, for when the bucket needs several options at once that
+/// would otherwise each need their own constructor. `GumbelTopBucket::new(scores, t)` is
+/// equivalent to `GumbelBucketBuilder::new().temperature(t).build(scores)`. Until `rng` is
+/// called, the builder uses the thread-local random number generator, which requires the
+/// `std` feature.
+#[derive(Debug, Clone)]
+pub struct GumbelBucketBuilder<F = f64, N = Gumbel, R = ThreadLocalRng> {
+    temperature: F,
+    top_k: Option<usize>,
+    noise: Option<N>,
+    rng: R,
+}
+
+impl GumbelBucketBuilder {
+    /// Create a new builder for a GumbelTopBucket of f64 scores, with a temperature of 1.0
+    /// and Gumbel noise. Use `GumbelBucketBuilder::<f32>::default()` for other float types.
+    pub fn new() -> GumbelBucketBuilder {
+        GumbelBucketBuilder::default()
+    }
+}
+
+impl<F: Float> Default for GumbelBucketBuilder<F> {
+    fn default() -> Self {
+        GumbelBucketBuilder {
+            temperature: F::one(),
+            top_k: None,
+            noise: None,
+            rng: ThreadLocalRng,
+        }
+    }
+}
+
+impl<F: Float, N: NoiseSource, R> GumbelBucketBuilder<F, N, R> {
+    /// Sets the temperature the Gumbel noise is scaled by; see `GumbelTopBucket::new`. This
+    /// has no effect once a custom noise source is set with `noise`.
+    pub fn temperature(mut self, temperature: F) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// Only keep the `k` highest noisy scores, like `GumbelTopBucket::new_top_k`.
+    pub fn top_k(mut self, k: usize) -> Self {
+        self.top_k = Some(k);
+        self
+    }
+
+    /// Perturb the scores with the given noise source instead of Gumbel noise, like
+    /// `GumbelTopBucket::new_with_noise`.
+    pub fn noise<M: NoiseSource>(self, noise: M) -> GumbelBucketBuilder<F, M, R> {
+        GumbelBucketBuilder {
+            temperature: self.temperature,
+            top_k: self.top_k,
+            noise: Some(noise),
+            rng: self.rng,
+        }
+    }
+
+    /// Draw the noise from the given random number generator instead of the thread-local
+    /// one. A mutable reference to a generator can be passed to keep using it afterwards.
+    pub fn rng<S: Rng>(self, rng: S) -> GumbelBucketBuilder<F, N, S> {
+        GumbelBucketBuilder {
+            temperature: self.temperature,
+            top_k: self.top_k,
+            noise: self.noise,
+            rng,
+        }
+    }
+}
+
+impl<F: Float, N: NoiseSource> GumbelBucketBuilder<F, N, ThreadLocalRng> {
+    /// Build the bucket from the given scores, using the thread-local random number generator.
+    /// This panics if the scores are empty or the temperature is invalid.
+    #[cfg(feature = "std")]
+    pub fn build<T>(self, scores: &[T]) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        self.rng(rand::thread_rng()).build(scores)
+    }
+}
+
+impl<F: Float, N: NoiseSource, R: Rng> GumbelBucketBuilder<F, N, R> {
+    /// Build the bucket from the given scores, using the random number generator set with
+    /// `rng`. This panics if the scores are empty or the temperature is invalid.
+    pub fn build<T>(mut self, scores: &[T]) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        // the noisy scores are left unsorted, so that with `top_k` only the kept ones are
+        // sorted, like in `new_top_k`.
+        let mut bucket = match self.noise {
+            Some(noise) => GumbelTopBucket::perturb_with_noise(scores, noise, &mut self.rng),
+            None => {
+                GumbelTopBucket::<F>::assert_valid(scores.len(), self.temperature);
+                GumbelTopBucket::perturb(
+                    scores.iter().copied(),
+                    iter::repeat(self.temperature),
+                    self.temperature,
+                    &mut self.rng,
+                )
+            }
+        };
+        match self.top_k {
+            Some(k) => bucket.keep_top_k(k),
+            None => GumbelTopBucket::sort_noisy_scores(&mut bucket.noisy_scores),
+        }
+        bucket
+    }
+}
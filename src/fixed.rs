@@ -0,0 +1,135 @@
+use num_traits::Float;
+use rand::distributions::Distribution;
+use rand::Rng;
+
+use crate::{gumbel_as, uniform, FloatAdd, GumbelTopBucket};
+
+/// A GumbelTopBucketN draws from the same distribution as the GumbelTopBucket, but for a
+/// number of scores `N` that is known at compile time. The scores and the noisy scores are
+/// stored in arrays instead of vectors, so the bucket never allocates and can live on the
+/// stack, which suits small fixed distributions in hot paths. The noisy scores are sorted in
+/// place, and ties are broken by index as in the GumbelTopBucket; for the same random number
+/// generator, both buckets draw in the same order.
+#[derive(Debug, Clone, Copy)]
+pub struct GumbelTopBucketN<const N: usize, F = f64> {
+    scores: [F; N],
+    temperature: F,
+    noisy_scores: [(usize, F); N],
+    // position of the next entry to draw in `noisy_scores`; everything before it
+    // has already been drawn.
+    cursor: usize,
+}
+
+impl<const N: usize, F: Float> GumbelTopBucketN<N, F> {
+    /// Create a new GumbelTopBucketN from an array of scores and a temperature. See
+    /// `GumbelTopBucket::new` for the meaning of the arguments. This panics if `N` is 0 or
+    /// the temperature is invalid.
+    #[cfg(feature = "std")]
+    pub fn new<T>(scores: &[T; N], temperature: F) -> GumbelTopBucketN<N, F>
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        GumbelTopBucketN::new_with_rng(scores, temperature, &mut rand::thread_rng())
+    }
+
+    /// Create a new GumbelTopBucketN like `new`, but drawing the Gumbel noise from the given
+    /// random number generator.
+    pub fn new_with_rng<T, R>(
+        scores: &[T; N],
+        temperature: F,
+        rng: &mut R,
+    ) -> GumbelTopBucketN<N, F>
+    where
+        T: FloatAdd<F> + Copy,
+        R: Rng + ?Sized,
+    {
+        GumbelTopBucket::<F>::assert_valid(N, temperature);
+        let mut bucket = GumbelTopBucketN {
+            scores: scores.map(|score| score.float_add(F::zero())),
+            temperature,
+            noisy_scores: [(0, F::zero()); N],
+            cursor: 0,
+        };
+        bucket.reset_noise_with_rng(temperature, rng);
+        bucket
+    }
+
+    /// Re-roll the Gumbel noise of the bucket with the given temperature, making every score
+    /// drawable again. See `GumbelTopBucket::reset_noise`.
+    #[cfg(feature = "std")]
+    pub fn reset_noise(&mut self, temperature: F) {
+        self.reset_noise_with_rng(temperature, &mut rand::thread_rng())
+    }
+
+    /// Re-roll the Gumbel noise of the bucket like `reset_noise`, but using the given random
+    /// number generator.
+    pub fn reset_noise_with_rng<R>(&mut self, temperature: F, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        let between = uniform();
+        for (i, entry) in self.noisy_scores.iter_mut().enumerate() {
+            *entry = (
+                i,
+                self.scores[i] + gumbel_as(between.sample(rng), temperature),
+            );
+        }
+        GumbelTopBucket::sort_noisy_scores(&mut self.noisy_scores);
+        self.temperature = temperature;
+        self.cursor = 0;
+    }
+
+    /// Draw a score from the bucket. This returns the index of the score in the original
+    /// array, as well as the *noisy* score. The score index will be removed from the bucket
+    /// and never sampled again. The method will return None if the bucket is empty.
+    #[must_use]
+    pub fn draw_with_score(&mut self) -> Option<(usize, F)> {
+        let entry = *self.noisy_scores.get(self.cursor)?;
+        self.cursor += 1;
+        Some(entry)
+    }
+
+    /// Draws a score from the bucket, returning its index in the original array. See
+    /// `draw_with_score`.
+    #[must_use]
+    pub fn draw(&mut self) -> Option<usize> {
+        let (idx, _) = self.draw_with_score()?;
+        Some(idx)
+    }
+
+    /// Look at the index that the next call to `draw` would return, without removing it
+    /// from the bucket.
+    #[must_use]
+    pub fn peek(&self) -> Option<usize> {
+        let &(idx, _) = self.noisy_scores.get(self.cursor)?;
+        Some(idx)
+    }
+
+    /// Returns the remaining `(index, noisy score)` pairs, in the order in which future calls
+    /// to `draw_with_score` would yield them.
+    pub fn as_slice(&self) -> &[(usize, F)] {
+        &self.noisy_scores[self.cursor..]
+    }
+
+    /// Returns the original, pre-noise score of the given index, or None if it is out of
+    /// bounds.
+    pub fn original_score(&self, idx: usize) -> Option<F> {
+        self.scores.get(idx).copied()
+    }
+
+    /// Returns the temperature the Gumbel noise of the bucket was scaled by.
+    pub fn temperature(&self) -> F {
+        self.temperature
+    }
+
+    /// Returns the number of scores that can still be drawn from the bucket.
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        N - self.cursor
+    }
+
+    /// Returns true if there are no scores left to draw from the bucket.
+    pub fn is_empty(&self) -> bool {
+        self.cursor == N
+    }
+}
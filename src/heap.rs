@@ -0,0 +1,129 @@
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use core::cmp::{Ordering, Reverse};
+use core::iter;
+use rand::distributions::Distribution;
+use rand::Rng;
+
+use crate::{cmp_noisy_scores, gumbel, uniform, F64Add, GumbelTopBucket};
+
+/// A noisy score that can be ordered, so that it can be stored in a `BinaryHeap`. Higher
+/// scores compare greater, and NaN compares less than every other score, matching the draw
+/// order of the GumbelTopBucket.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct OrderedF64(pub(crate) f64);
+
+impl PartialEq for OrderedF64 {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OrderedF64 {}
+
+impl PartialOrd for OrderedF64 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedF64 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // `cmp_noisy_scores` puts the higher score first, so it is reversed here.
+        cmp_noisy_scores(other.0, self.0)
+    }
+}
+
+/// A GumbelHeapBucket draws from the same distribution as the GumbelTopBucket, but keeps the
+/// noisy scores in a binary heap instead of a sorted vector. Building it is O(n) instead of
+/// O(n log n), but each draw is O(log n) instead of O(1). This makes it the better choice
+/// when only a handful of scores are drawn from a very large bucket, while the
+/// GumbelTopBucket is better when most of the bucket is drawn. It also does not store the
+/// original scores, so it uses less memory.
+#[derive(Debug, Clone)]
+pub struct GumbelHeapBucket {
+    // the index is reversed so that ties are popped in input order, like in the
+    // GumbelTopBucket.
+    noisy_scores: BinaryHeap<(OrderedF64, Reverse<usize>)>,
+}
+
+impl GumbelHeapBucket {
+    /// Create a new GumbelHeapBucket from a slice of scores and a temperature. See
+    /// `GumbelTopBucket::new` for the meaning of the arguments. This panics if the scores
+    /// are empty or the temperature is invalid.
+    #[cfg(feature = "std")]
+    pub fn new<T>(scores: &[T], temperature: f64) -> GumbelHeapBucket
+    where
+        T: F64Add + Copy,
+    {
+        GumbelHeapBucket::new_with_rng(scores, temperature, &mut rand::thread_rng())
+    }
+
+    /// Create a new GumbelHeapBucket like `new`, but drawing the Gumbel noise from the given
+    /// random number generator.
+    pub fn new_with_rng<T, R>(scores: &[T], temperature: f64, rng: &mut R) -> GumbelHeapBucket
+    where
+        T: F64Add + Copy,
+        R: Rng + ?Sized,
+    {
+        GumbelTopBucket::assert_valid(scores.len(), temperature);
+        let between = uniform();
+        let noisy_scores: Vec<(OrderedF64, Reverse<usize>)> = scores
+            .iter()
+            .zip(iter::repeat_with(|| {
+                gumbel(between.sample(rng), temperature)
+            }))
+            .enumerate()
+            .map(|(i, (&score, noise))| (OrderedF64(score.float_add(noise)), Reverse(i)))
+            .collect();
+
+        GumbelHeapBucket {
+            noisy_scores: BinaryHeap::from(noisy_scores),
+        }
+    }
+
+    /// Draw a score from the bucket. This returns the index of the score in the original list,
+    /// as well as the *noisy* score. The score index will be removed from the bucket and never
+    /// sampled again. The method will return None if the bucket is empty.
+    #[must_use]
+    pub fn draw_with_score(&mut self) -> Option<(usize, f64)> {
+        let (noisy_score, Reverse(idx)) = self.noisy_scores.pop()?;
+        Some((idx, noisy_score.0))
+    }
+
+    /// Draws a score from the bucket. This returns the index of the score in the original list.
+    /// The score index will be removed from the bucket and never sampled again. The method will
+    /// return None if the bucket is empty.
+    #[must_use]
+    pub fn draw(&mut self) -> Option<usize> {
+        let (idx, _) = self.draw_with_score()?;
+        Some(idx)
+    }
+
+    /// Look at the score that the next call to `draw_with_score` would return, without
+    /// removing it from the bucket.
+    #[must_use]
+    pub fn peek_with_score(&self) -> Option<(usize, f64)> {
+        let &(noisy_score, Reverse(idx)) = self.noisy_scores.peek()?;
+        Some((idx, noisy_score.0))
+    }
+
+    /// Look at the index that the next call to `draw` would return, without removing it
+    /// from the bucket.
+    #[must_use]
+    pub fn peek(&self) -> Option<usize> {
+        let (idx, _) = self.peek_with_score()?;
+        Some(idx)
+    }
+
+    /// Returns the number of scores that can still be drawn from the bucket.
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.noisy_scores.len()
+    }
+
+    /// Returns true if there are no scores left to draw from the bucket.
+    pub fn is_empty(&self) -> bool {
+        self.noisy_scores.is_empty()
+    }
+}
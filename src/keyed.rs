@@ -0,0 +1,104 @@
+use alloc::vec::Vec;
+use num_traits::Float;
+use rand::Rng;
+
+use crate::{FloatAdd, GumbelTopBucket};
+
+/// A GumbelTopBucketKeyed is a GumbelTopBucket whose scores are labelled by arbitrary keys
+/// instead of by their position in a list. It wraps a GumbelTopBucket over the positions of
+/// the scores, along with the keys in the same order, so that draws can map the positions
+/// back to their keys.
+#[derive(Debug, Clone)]
+pub struct GumbelTopBucketKeyed<K, F = f64> {
+    bucket: GumbelTopBucket<F>,
+    keys: Vec<K>,
+}
+
+impl<K, F: Float> GumbelTopBucketKeyed<K, F> {
+    /// Create a new GumbelTopBucketKeyed from `(key, score)` pairs and a temperature, such as
+    /// a `HashMap<K, f64>` or a `Vec<(K, f64)>`. See `GumbelTopBucket::new` for the meaning of
+    /// the temperature. This panics if there are no pairs or the temperature is invalid.
+    #[cfg(feature = "std")]
+    pub fn new<I, T>(pairs: I, temperature: F) -> GumbelTopBucketKeyed<K, F>
+    where
+        I: IntoIterator<Item = (K, T)>,
+        T: FloatAdd<F> + Copy,
+    {
+        GumbelTopBucketKeyed::new_with_rng(pairs, temperature, &mut rand::thread_rng())
+    }
+
+    /// Create a new GumbelTopBucketKeyed like `new`, but drawing the Gumbel noise from the
+    /// given random number generator. For a `HashMap`, the draws are only reproducible if its
+    /// iteration order is, too.
+    pub fn new_with_rng<I, T, R>(
+        pairs: I,
+        temperature: F,
+        rng: &mut R,
+    ) -> GumbelTopBucketKeyed<K, F>
+    where
+        I: IntoIterator<Item = (K, T)>,
+        T: FloatAdd<F> + Copy,
+        R: Rng + ?Sized,
+    {
+        let mut keys = Vec::new();
+        let scores = pairs.into_iter().map(|(key, score)| {
+            keys.push(key);
+            score
+        });
+        let bucket = GumbelTopBucket::from_scores_with_rng(scores, temperature, rng);
+        GumbelTopBucketKeyed { bucket, keys }
+    }
+
+    /// Draw a key from the bucket, together with its *noisy* score. The key will
+    /// never be drawn again. Returns None if the bucket is empty.
+    #[must_use]
+    pub fn draw_with_score(&mut self) -> Option<(K, F)>
+    where
+        K: Clone,
+    {
+        let (idx, noisy_score) = self.bucket.draw_with_score()?;
+        Some((self.keys[idx].clone(), noisy_score))
+    }
+
+    /// Draw a key from the bucket. The key will never be drawn again. Returns None if the bucket
+    /// is empty.
+    #[must_use]
+    pub fn draw(&mut self) -> Option<K>
+    where
+        K: Clone,
+    {
+        let idx = self.bucket.draw()?;
+        Some(self.keys[idx].clone())
+    }
+
+    /// Look at the key that the next call to `draw` would return, without removing it from the
+    /// bucket.
+    #[must_use]
+    pub fn peek(&self) -> Option<&K> {
+        let idx = self.bucket.peek()?;
+        Some(&self.keys[idx])
+    }
+
+    /// Returns the number of keys that can still be drawn from the bucket.
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.bucket.remaining()
+    }
+
+    /// Returns true if there are no keys left to draw from the bucket.
+    pub fn is_empty(&self) -> bool {
+        self.bucket.is_empty()
+    }
+
+    /// Returns the key of the given position in the underlying bucket, i.e. the position of
+    /// its pair in the iteration order the bucket was built from.
+    pub fn key(&self, idx: usize) -> Option<&K> {
+        self.keys.get(idx)
+    }
+
+    /// Returns the underlying GumbelTopBucket over the positions of the keys, for the methods
+    /// that are not mirrored by the keyed bucket.
+    pub fn as_bucket(&self) -> &GumbelTopBucket<F> {
+        &self.bucket
+    }
+}
@@ -1,4 +1,38 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::iter;
+use num_traits::Float;
 use rand::distributions::{Distribution, Uniform};
+use rand::Rng;
+
+mod builder;
+mod fixed;
+mod heap;
+mod keyed;
+#[cfg(feature = "llm")]
+mod llm;
+mod noise;
+mod reservoir;
+mod sampler;
+mod shared;
+
+pub use builder::{GumbelBucketBuilder, ThreadLocalRng};
+pub use fixed::GumbelTopBucketN;
+pub use heap::GumbelHeapBucket;
+pub use keyed::GumbelTopBucketKeyed;
+#[cfg(all(feature = "llm", feature = "std"))]
+pub use llm::sample_tokens;
+#[cfg(feature = "llm")]
+pub use llm::sample_tokens_with_rng;
+pub use noise::{Gumbel, NoiseSource};
+pub use reservoir::GumbelReservoir;
+pub use sampler::WeightedWithoutReplacement;
+pub use shared::SharedGumbelBucket;
 
 /// A GumbelTopBucket is a bucket that can be used to draw from a discrete
 /// distribution, similar to a softmax. The difference is that the GumbelTopBucket
@@ -9,20 +43,58 @@ use rand::distributions::{Distribution, Uniform};
 /// even if the scores are the same. This is useful for sampling without
 /// replacement. It is important to note that this comes at a memory cost,
 /// as we have to store a whole vector of noisy scores, on top of the original
-/// scores.
+/// scores. If only a few scores are ever drawn from a very large bucket, see
+/// `GumbelHeapBucket`, which trades slower draws for a cheaper construction.
+///
+/// The bucket is generic over the float type `F` it stores scores and temperatures in,
+/// which defaults to f64. Using `GumbelTopBucket<f32>` halves the memory taken by both
+/// vectors, at the cost of precision in the noisy scores.
+///
+/// Equal noisy scores, which become likely with integer or quantized scores and a low
+/// temperature (or with f32), are always drawn in the order of their indices in the original
+/// list. The noisy scores are sorted with an unstable sort, but the index is part of the
+/// comparison, so the order is total and there is no separate stable-sort constructor; the
+/// unstable sort is in place, while a stable one would allocate an auxiliary buffer.
+/// Together with a seeded random number generator, this makes the draw order fully
+/// reproducible.
+///
+/// The bucket does not own a random number generator: every method that rolls noise, such
+/// as `push` or `reset_noise`, has a `*_with_rng` variant taking the generator by reference.
+/// To continue a bucket with a freshly seeded generator, pass it to these methods; this
+/// only affects the noise rolled from then on, not the noisy scores already computed.
 #[derive(Debug, Clone)]
-pub struct GumbelTopBucket {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GumbelTopBucket<F = f64> {
+    // the number of remaining entries, which follow the cursor; the entries after them have
+    // been drawn from the back with `draw_min`.
     scores_len: usize,
-    noisy_scores: Vec<(usize, f64)>,
+    scores: Vec<F>,
+    // the temperature the noise was scaled by, or NaN if there is no single Gumbel temperature.
+    temperature: F,
+    noisy_scores: Vec<(usize, F)>,
+    // position of the next entry to draw in `noisy_scores`; everything before it
+    // has already been drawn.
+    cursor: usize,
 }
 
 /// This trait is needed for the GumbelTopBucket to work with multiple score types.
-/// It is implemented for f32 and f64, but can be implemented for other types as well, as
-/// long as they have a way to add a f64 to themselves. It is reccomended to implement
-/// the `float_add` function using the `#[inline]` attribute, as it is called for each
-/// score in the bucket.
+/// It is implemented for f32 and f64, as well as for the common integer types, but can be
+/// implemented for other types as well, as long as they have a way to add a f64 to themselves.
+/// Every `F64Add` type can be used as the scores of a `GumbelTopBucket<f64>` through
+/// `FloatAdd<f64>`.
+/// It is reccomended to implement the `float_add` function using the `#[inline]` attribute, as
+/// it is called for each score in the bucket. Note that integers are converted to f64, which is
+/// only exact for magnitudes up to 2^53; larger `u64` and `i64` scores are rounded, which
+/// `is_exact` reports and `try_new` rejects.
 pub trait F64Add {
     fn float_add(self, other: f64) -> f64;
+
+    /// Returns whether the score is converted to f64 without losing precision. This defaults
+    /// to true, and is false for `u64` and `i64` scores beyond 2^53 in magnitude.
+    #[inline]
+    fn is_exact(&self) -> bool {
+        true
+    }
 }
 
 impl F64Add for f32 {
@@ -39,61 +111,2436 @@ impl F64Add for f64 {
     }
 }
 
-impl GumbelTopBucket {
+macro_rules! impl_f64_add_for_int {
+    ($($t:ty),*) => {
+        $(
+            impl F64Add for $t {
+                #[inline]
+                fn float_add(self, other: f64) -> f64 {
+                    (self as f64) + other
+                }
+            }
+        )*
+    };
+}
+
+impl_f64_add_for_int!(u8, u16, u32, i32);
+
+// Larger `u64`/`i64` values are rounded to the nearest representable f64 by `as f64`, so
+// distinct large weights may end up with the same score. Every integer up to 2^53 in
+// magnitude is exact.
+macro_rules! impl_f64_add_for_wide_int {
+    ($($t:ty),*) => {
+        $(
+            impl F64Add for $t {
+                #[inline]
+                fn float_add(self, other: f64) -> f64 {
+                    (self as f64) + other
+                }
+
+                #[inline]
+                fn is_exact(&self) -> bool {
+                    (*self as i128).unsigned_abs() <= 1 << f64::MANTISSA_DIGITS
+                }
+            }
+        )*
+    };
+}
+
+impl_f64_add_for_wide_int!(u64, i64);
+
+#[cfg(feature = "half")]
+impl F64Add for half::f16 {
+    #[inline]
+    fn float_add(self, other: f64) -> f64 {
+        self.to_f64() + other
+    }
+}
+
+#[cfg(feature = "half")]
+impl F64Add for half::bf16 {
+    #[inline]
+    fn float_add(self, other: f64) -> f64 {
+        self.to_f64() + other
+    }
+}
+
+/// The generalization of `F64Add` to buckets of any float type `F`: the score is converted
+/// to `F` and added to `other`. It is implemented for every `F64Add` type with `F = f64`,
+/// and for f32 (and the `half` types, with the `half` feature) with `F = f32`.
+pub trait FloatAdd<F> {
+    fn float_add(self, other: F) -> F;
+
+    /// Returns whether the score is converted to `F` without losing precision. See
+    /// `F64Add::is_exact`.
+    #[inline]
+    fn is_exact(&self) -> bool {
+        true
+    }
+}
+
+impl<T: F64Add> FloatAdd<f64> for T {
+    #[inline]
+    fn float_add(self, other: f64) -> f64 {
+        F64Add::float_add(self, other)
+    }
+
+    #[inline]
+    fn is_exact(&self) -> bool {
+        F64Add::is_exact(self)
+    }
+}
+
+impl FloatAdd<f32> for f32 {
+    #[inline]
+    fn float_add(self, other: f32) -> f32 {
+        self + other
+    }
+}
+
+#[cfg(feature = "half")]
+impl FloatAdd<f32> for half::f16 {
+    #[inline]
+    fn float_add(self, other: f32) -> f32 {
+        self.to_f32() + other
+    }
+}
+
+#[cfg(feature = "half")]
+impl FloatAdd<f32> for half::bf16 {
+    #[inline]
+    fn float_add(self, other: f32) -> f32 {
+        self.to_f32() + other
+    }
+}
+
+/// The errors that can occur when building a GumbelTopBucket through one of the `try_*`
+/// constructors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GumbelError {
+    /// The temperature was negative or not finite. Despite the name, which predates support
+    /// for a temperature of 0, a temperature of 0 is accepted.
+    NonPositiveTemperature,
+    /// The slice of scores was empty.
+    EmptyScores,
+    /// The score at the given index in the original list was NaN or infinite.
+    NonFiniteScore(usize),
+    /// The score at the given index in the original list is an integer too large to be
+    /// represented exactly as a float; see `F64Add::is_exact`.
+    InexactScore(usize),
+}
+
+impl core::fmt::Display for GumbelError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            GumbelError::NonPositiveTemperature => {
+                write!(f, "temperature must be finite and not negative")
+            }
+            GumbelError::EmptyScores => write!(f, "scores must not be empty"),
+            GumbelError::NonFiniteScore(idx) => {
+                write!(f, "score at index {} is not finite", idx)
+            }
+            GumbelError::InexactScore(idx) => {
+                write!(f, "score at index {} cannot be represented exactly", idx)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for GumbelError {}
+
+/// Orders two noisy scores such that higher scores come first and NaN scores come after
+/// every other score.
+#[inline]
+fn cmp_noisy_scores<F: Float>(a: F, b: F) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (false, false) => b.partial_cmp(&a).unwrap_or(Ordering::Equal),
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (true, true) => Ordering::Equal,
+    }
+}
+
+/// Orders two `(index, noisy score)` entries by `cmp_noisy_scores`, breaking ties by the index
+/// in the original list so that equal noisy scores, such as infinite ones, are drawn in input
+/// order.
+#[inline]
+fn cmp_entries<F: Float>(a: &(usize, F), b: &(usize, F)) -> Ordering {
+    cmp_noisy_scores(a.1, b.1).then(a.0.cmp(&b.0))
+}
+
+/// Maps a noisy score to an integer key that orders like `cmp_noisy_scores`: higher scores get
+/// lower keys, NaN gets the highest key, and both zeros get the same key. For finite and
+/// infinite f64 scores, the key is the bit pattern of the score, with the sign bit flipped for
+/// positive scores and every bit flipped for negative ones, which orders them ascending as
+/// unsigned integers, inverted to get the descending order. Scores of other float types are
+/// converted to f64 first, which is exact for f32 and the `half` types. It is only public so
+/// that the benches can measure the sort the bucket does; it is not part of the API.
+#[doc(hidden)]
+#[inline]
+pub fn draw_order_key<F: Float>(noisy_score: F) -> u64 {
+    let noisy_score = noisy_score.to_f64().unwrap_or(f64::NAN);
+    if noisy_score.is_nan() {
+        return u64::MAX;
+    }
+    // adding 0 turns -0 into 0, which would otherwise get a different key.
+    let bits = (noisy_score + 0.0).to_bits();
+    let ascending = if bits >> 63 == 1 {
+        !bits
+    } else {
+        bits | 1 << 63
+    };
+    !ascending
+}
+
+/// An `(index, noisy score)` entry ordered by draw order, such that the entry drawn first
+/// compares greatest. A `BinaryHeap` of them therefore has the highest noisy score on top.
+struct DrawOrder<F>((usize, F));
+
+impl<F: Float> PartialEq for DrawOrder<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<F: Float> Eq for DrawOrder<F> {}
+
+impl<F: Float> PartialOrd for DrawOrder<F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<F: Float> Ord for DrawOrder<F> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // `cmp_entries` puts the entry drawn first first, so it is reversed here.
+        cmp_entries(&other.0, &self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// The generator behind `GumbelTopBucket::new_fast`, seeded once per thread.
+    static FAST_RNG: core::cell::RefCell<rand::rngs::SmallRng> =
+        core::cell::RefCell::new(rand::SeedableRng::from_entropy());
+}
+
+/// A score that is already of the bucket's float type, for the constructors that compute the
+/// scores themselves before building the bucket.
+#[derive(Clone, Copy)]
+struct Native<F>(F);
+
+impl<F: Float> FloatAdd<F> for Native<F> {
+    #[inline]
+    fn float_add(self, other: F) -> F {
+        self.0 + other
+    }
+}
+
+/// Transforms a uniform sample `x` in (0, 1) into a sample of Gumbel(0, temperature).
+#[inline]
+fn gumbel(x: f64, temperature: f64) -> f64 {
+    -Float::ln(-Float::ln(x)) * temperature
+}
+
+/// Like `gumbel`, but scaled by a temperature of the bucket's float type. The noise itself is
+/// always computed in f64, as `x` may be too close to 0 or 1 to be represented in `F`.
+#[inline]
+fn gumbel_as<F: Float>(x: f64, temperature: F) -> F {
+    cast::<F>(gumbel(x, 1.0)) * temperature
+}
+
+/// Converts a f64 into the float type of the bucket, falling back to NaN if it can't be
+/// represented.
+#[inline]
+fn cast<F: Float>(x: f64) -> F {
+    F::from(x).unwrap_or_else(F::nan)
+}
+
+/// The distance from 0 and 1 at which the uniform samples the Gumbel noise is derived from
+/// are clamped, to avoid taking the logarithm of 0. It bounds the noise to roughly
+/// `[-ln(-ln(1e-10)), -ln(1e-10)]`, i.e. about `[-3.1, 23.0]` at a temperature of 1, which
+/// cuts off tails with a probability of about 1e-10 each. See `gumbel_noise_eps`.
+pub const UNIFORM_EPS: f64 = 1e-10;
+
+/// The uniform distribution the Gumbel noise is derived from. The bounds are kept
+/// away from 0 and 1 to avoid taking the logarithm of 0.
+#[inline]
+fn uniform() -> Uniform<f64> {
+    uniform_with_eps(UNIFORM_EPS)
+}
+
+/// Like `uniform`, but with bounds that are `eps` away from 0 and 1.
+#[inline]
+fn uniform_with_eps(eps: f64) -> Uniform<f64> {
+    assert!(eps > 0.0 && eps < 0.5, "eps must be in (0, 0.5)");
+    Uniform::from(eps..(1.0 - eps))
+}
+
+impl<F: Float> GumbelTopBucket<F> {
     /// Create a new GumbelTopBucket from a slice of scores and a temperature. Typically,
-    /// scores should be in the range [0, 1], and the temperature should be > 0. It is
+    /// scores should be in the range [0, 1], and the temperature should be >= 0. It is
     /// possible to use scores outside of this range, but the results may be unexpected;
     /// the temperature can be utilized to adjust the range of the scores. A temperature
-    /// of 1.0 is recommended for most use cases.
-    pub fn new<T>(scores: &[T], temperature: f64) -> GumbelTopBucket
+    /// of 1.0 is recommended for most use cases; see `temperature` for its limits. A
+    /// temperature of exactly 0 is supported as a deterministic mode: the noise vanishes, so
+    /// the draws are a descending sort of the raw scores, with ties broken by index. This
+    /// panics if the scores are empty or the temperature is invalid; see `try_new` for a
+    /// non-panicking version. NaN scores are accepted, but they are always drawn last, after
+    /// every other score. Infinite scores are accepted as well, which is useful for forcing or
+    /// masking out entries: `+inf` scores are always drawn first, in input order, and `-inf`
+    /// scores are only drawn once every finite score has been drawn.
+    #[cfg(feature = "std")]
+    pub fn new<T>(scores: &[T], temperature: F) -> GumbelTopBucket<F>
     where
-        T: F64Add + Copy,
+        T: FloatAdd<F> + Copy,
+    {
+        Self::new_with_rng(scores, temperature, &mut rand::thread_rng())
+    }
+
+    /// Create a new GumbelTopBucket like `new`, but drawing the Gumbel noise from the
+    /// given random number generator instead of the thread-local one. Passing two
+    /// generators seeded with the same value (e.g. `StdRng::seed_from_u64`) yields
+    /// identical buckets, which makes sampling sequences reproducible in tests and
+    /// simulations. When building many buckets in a loop, hoisting a single generator out
+    /// of the loop also avoids looking up the thread-local generator on every iteration.
+    pub fn new_with_rng<T, R>(scores: &[T], temperature: F, rng: &mut R) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+        R: Rng + ?Sized,
+    {
+        Self::assert_valid(scores.len(), temperature);
+        Self::build(
+            scores.iter().copied(),
+            iter::repeat(temperature),
+            temperature,
+            rng,
+        )
+    }
+
+    /// Create a new GumbelTopBucket like `new`, but with Gumbel noise of the given location
+    /// μ instead of 0, i.e. `location - temperature * ln(-ln(u))`, to match the
+    /// parameterizations used in some of the literature. As the location shifts every noisy
+    /// score by the same amount, the draws follow the same distribution as with `new`; only
+    /// the noisy scores returned by `draw_with_score` and its variants differ. `reset_noise`
+    /// and the other methods that re-roll the noise do not keep the location. This panics if
+    /// the scores are empty, the temperature is invalid, or the location is not finite.
+    #[cfg(feature = "std")]
+    pub fn new_with_location<T>(scores: &[T], location: F, temperature: F) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        Self::new_with_location_with_rng(scores, location, temperature, &mut rand::thread_rng())
+    }
+
+    /// Create a new GumbelTopBucket like `new_with_location`, but drawing the Gumbel noise
+    /// from the given random number generator.
+    pub fn new_with_location_with_rng<T, R>(
+        scores: &[T],
+        location: F,
+        temperature: F,
+        rng: &mut R,
+    ) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+        R: Rng + ?Sized,
+    {
+        Self::assert_valid(scores.len(), temperature);
+        assert!(location.is_finite(), "the location must be finite");
+        let mut bucket = Self::perturb(
+            scores.iter().copied(),
+            iter::repeat(temperature),
+            temperature,
+            rng,
+        );
+        for (_, noisy_score) in bucket.noisy_scores.iter_mut() {
+            *noisy_score = *noisy_score + location;
+        }
+        Self::sort_noisy_scores(&mut bucket.noisy_scores);
+        bucket
+    }
+
+    /// Create a new GumbelTopBucket like `new`, but drawing the Gumbel noise from a thread-local
+    /// `SmallRng`, seeded from the operating system once per thread, instead of the
+    /// thread-local `ThreadRng`. `SmallRng` generates numbers much faster, although building a
+    /// bucket is mostly spent on the logarithms of the noise and the sort, so the gain is the
+    /// largest for small buckets. Its statistical quality is plenty for Monte Carlo
+    /// simulations and sampling, but it is not cryptographically secure:
+    /// its output can be predicted from a few samples, so `new` should be preferred when an
+    /// adversary must not be able to guess or influence the draws.
+    #[cfg(feature = "std")]
+    pub fn new_fast<T>(scores: &[T], temperature: F) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        FAST_RNG.with(|rng| Self::new_with_rng(scores, temperature, &mut *rng.borrow_mut()))
+    }
+
+    /// Create a new GumbelTopBucket like `new`, but returning an error instead of panicking
+    /// if the scores or the temperature are invalid. The temperature must be finite and
+    /// at least 0, and the scores must be non-empty and finite; unlike `new`, NaN
+    /// scores are rejected, and so are integer scores that would be rounded when converted
+    /// to a float, such as `u64` weights beyond 2^53.
+    #[cfg(feature = "std")]
+    pub fn try_new<T>(scores: &[T], temperature: F) -> Result<GumbelTopBucket<F>, GumbelError>
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        Self::try_new_with_rng(scores, temperature, &mut rand::thread_rng())
+    }
+
+    /// Create a new GumbelTopBucket like `try_new`, but drawing the Gumbel noise from the
+    /// given random number generator. See `new_with_rng`.
+    pub fn try_new_with_rng<T, R>(
+        scores: &[T],
+        temperature: F,
+        rng: &mut R,
+    ) -> Result<GumbelTopBucket<F>, GumbelError>
+    where
+        T: FloatAdd<F> + Copy,
+        R: Rng + ?Sized,
+    {
+        Self::validate_temperature(temperature)?;
+        Self::validate_scores(scores)?;
+        Ok(Self::build(
+            scores.iter().copied(),
+            iter::repeat(temperature),
+            temperature,
+            rng,
+        ))
+    }
+
+    /// Create a new GumbelTopBucket from an iterator of scores, applying the noise as the
+    /// scores are consumed instead of requiring them to be collected into a slice first.
+    /// The original index of each score is its position in the iteration order (0, 1, 2,
+    /// ...). Like `new`, this panics if there are no scores or the temperature is invalid.
+    #[cfg(feature = "std")]
+    pub fn from_scores<I, T>(scores: I, temperature: F) -> GumbelTopBucket<F>
+    where
+        I: IntoIterator<Item = T>,
+        T: FloatAdd<F> + Copy,
+    {
+        Self::from_scores_with_rng(scores, temperature, &mut rand::thread_rng())
+    }
+
+    /// Create a new GumbelTopBucket like `from_scores`, but drawing the Gumbel noise from
+    /// the given random number generator. See `new_with_rng`.
+    pub fn from_scores_with_rng<I, T, R>(
+        scores: I,
+        temperature: F,
+        rng: &mut R,
+    ) -> GumbelTopBucket<F>
+    where
+        I: IntoIterator<Item = T>,
+        T: FloatAdd<F> + Copy,
+        R: Rng + ?Sized,
+    {
+        if let Err(err) = Self::validate_temperature(temperature) {
+            panic!("{}", err);
+        }
+        let bucket = Self::build(scores, iter::repeat(temperature), temperature, rng);
+        if bucket.noisy_scores.is_empty() {
+            panic!("{}", GumbelError::EmptyScores);
+        }
+        bucket
+    }
+
+    /// Create a new GumbelTopBucket from a slice of arbitrary items, computing the score of each
+    /// item with `score_fn`. This avoids collecting the scores into an intermediate vector when
+    /// they are a field of, or are computed from, larger items. The index of each score in the
+    /// original list is the position of its item in the slice. Like `new`, this panics if there
+    /// are no items or the temperature is invalid.
+    #[cfg(feature = "std")]
+    pub fn new_by_ref<T, S>(items: &[T], temperature: F, score_fn: S) -> GumbelTopBucket<F>
+    where
+        S: Fn(&T) -> F,
+    {
+        Self::new_by_ref_with_rng(items, temperature, score_fn, &mut rand::thread_rng())
+    }
+
+    /// Create a new GumbelTopBucket like `new_by_ref`, but drawing the Gumbel noise from the
+    /// given random number generator.
+    pub fn new_by_ref_with_rng<T, S, R>(
+        items: &[T],
+        temperature: F,
+        score_fn: S,
+        rng: &mut R,
+    ) -> GumbelTopBucket<F>
+    where
+        S: Fn(&T) -> F,
+        R: Rng + ?Sized,
+    {
+        Self::assert_valid(items.len(), temperature);
+        Self::build(
+            items.iter().map(|item| Native(score_fn(item))),
+            iter::repeat(temperature),
+            temperature,
+            rng,
+        )
+    }
+
+    /// Create a new GumbelTopBucket from a one-dimensional `ndarray` view of scores, such as a
+    /// row of an `Array2`. The elements are iterated in the logical order of the view, so this
+    /// also works for non-contiguous views, for which `as_slice` would fail. Like `new`, this
+    /// panics if the view is empty or the temperature is invalid.
+    #[cfg(all(feature = "ndarray", feature = "std"))]
+    pub fn new_from_ndarray<T>(
+        scores: ndarray::ArrayView1<'_, T>,
+        temperature: F,
+    ) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        Self::new_from_ndarray_with_rng(scores, temperature, &mut rand::thread_rng())
+    }
+
+    /// Create a new GumbelTopBucket like `new_from_ndarray`, but drawing the Gumbel noise from
+    /// the given random number generator.
+    #[cfg(feature = "ndarray")]
+    pub fn new_from_ndarray_with_rng<T, R>(
+        scores: ndarray::ArrayView1<'_, T>,
+        temperature: F,
+        rng: &mut R,
+    ) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+        R: Rng + ?Sized,
+    {
+        Self::assert_valid(scores.len(), temperature);
+        Self::build(
+            scores.iter().copied(),
+            iter::repeat(temperature),
+            temperature,
+            rng,
+        )
+    }
+
+    /// Create a new GumbelTopBucket where the Gumbel noise of each score is scaled by its own
+    /// temperature, instead of a single temperature shared by all scores. This is strictly
+    /// more general than `new`, and is useful when some scores should be sampled more
+    /// sharply than others. This panics if `temperatures` does not have the same length as
+    /// `scores`, if the scores are empty, or if any of the temperatures is invalid.
+    #[cfg(feature = "std")]
+    pub fn new_with_temperatures<T>(scores: &[T], temperatures: &[F]) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        Self::new_with_temperatures_with_rng(scores, temperatures, &mut rand::thread_rng())
+    }
+
+    /// Create a new GumbelTopBucket like `new_with_temperatures`, but drawing the Gumbel
+    /// noise from the given random number generator. See `new_with_rng`.
+    pub fn new_with_temperatures_with_rng<T, R>(
+        scores: &[T],
+        temperatures: &[F],
+        rng: &mut R,
+    ) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+        R: Rng + ?Sized,
     {
-        let scores_len = scores.len();
-        let noises = GumbelTopBucket::gumbel_noise(scores_len, temperature);
-        let mut noisy_scores: Vec<(usize, f64)> = scores
+        assert_eq!(
+            scores.len(),
+            temperatures.len(),
+            "scores and temperatures must have the same length"
+        );
+        if let Some(err) = temperatures
             .iter()
+            .find_map(|&temperature| Self::validate_temperature(temperature).err())
+        {
+            panic!("{}", err);
+        }
+        if scores.is_empty() {
+            panic!("{}", GumbelError::EmptyScores);
+        }
+        Self::build(
+            scores.iter().copied(),
+            temperatures.iter().copied(),
+            F::nan(),
+            rng,
+        )
+    }
+
+    /// Create a new GumbelTopBucket like `new`, but generating the noise and sorting the
+    /// noisy scores in parallel using rayon. This only pays off for large slices of scores
+    /// (think hundreds of thousands), where the construction is dominated by the noise and
+    /// the sort; for small slices, `new` is faster. Each rayon worker uses its own
+    /// thread-local random number generator.
+    #[cfg(feature = "rayon")]
+    pub fn new_par<T>(scores: &[T], temperature: F) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy + Send + Sync,
+        F: Send + Sync,
+    {
+        use rayon::prelude::*;
+
+        Self::assert_valid(scores.len(), temperature);
+        let between = uniform();
+        let mut noisy_scores: Vec<(usize, F)> = scores
+            .par_iter()
             .enumerate()
-            .map(|(i, &score)| (i, score.float_add(noises[i])))
+            .map_init(rand::thread_rng, |rng, (i, &score)| {
+                (
+                    i,
+                    score.float_add(gumbel_as(between.sample(rng), temperature)),
+                )
+            })
+            .collect();
+        noisy_scores.par_sort_unstable_by(cmp_entries);
+        let scores = scores
+            .par_iter()
+            .map(|&score| score.float_add(F::zero()))
             .collect();
-        noisy_scores
-            .sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
 
         GumbelTopBucket {
-            scores_len,
+            scores_len: noisy_scores.len(),
+            scores,
+            temperature,
             noisy_scores,
+            cursor: 0,
         }
     }
 
-    /// Generate a vector of Gumbel noise. This is used internally to generate the
-    /// noisy scores. It is exposed as a public function in case you want to use
-    /// the Gumbel noise for something else.
-    pub fn gumbel_noise(size: usize, temperature: f64) -> Vec<f64> {
-        let mut rng = rand::thread_rng();
-        let between = Uniform::from(1e-10f64..(1.0 - 1e-10f64));
-        let u: Vec<f64> = between.sample_iter(&mut rng).take(size).collect();
-        u.iter()
-            .map(|&x| -((-(x.ln())).ln()) * temperature)
-            .collect()
+    /// Create a new GumbelTopBucket from a slice of scores, perturbing them with the given
+    /// noise source instead of Gumbel noise. `GumbelTopBucket::new(scores, t)` is equivalent
+    /// to `GumbelTopBucket::new_with_noise(scores, Gumbel::new(t))`. Note that the methods
+    /// that rely on the Gumbel distribution, like `probability`, are meaningless for other
+    /// kinds of noise, so the bucket records no temperature. This panics if the scores are
+    /// empty.
+    #[cfg(feature = "std")]
+    pub fn new_with_noise<T, N>(scores: &[T], noise: N) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+        N: NoiseSource,
+    {
+        Self::new_with_noise_with_rng(scores, noise, &mut rand::thread_rng())
     }
 
-    /// Draw a score from the bucket. This returns the index of the score in the original list,
-    /// as well as the *noisy* score. The score index will be removed from the list and never
-    /// sampled again. The method will return None if the bucket is empty.
-    pub fn draw_with_score(&mut self) -> Option<(usize, f64)> {
-        if self.scores_len == 0 {
-            return None;
+    /// Create a new GumbelTopBucket like `new_with_noise`, but sampling the noise with the
+    /// given random number generator.
+    pub fn new_with_noise_with_rng<T, N, R>(
+        scores: &[T],
+        noise: N,
+        rng: &mut R,
+    ) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+        N: NoiseSource,
+        R: Rng + ?Sized,
+    {
+        let mut bucket = Self::perturb_with_noise(scores, noise, rng);
+        Self::sort_noisy_scores(&mut bucket.noisy_scores);
+        bucket
+    }
+
+    /// Like `new_with_noise_with_rng`, but leaves the noisy scores unsorted, like `perturb`.
+    fn perturb_with_noise<T, N, R>(scores: &[T], noise: N, rng: &mut R) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+        N: NoiseSource,
+        R: Rng + ?Sized,
+    {
+        if scores.is_empty() {
+            panic!("{}", GumbelError::EmptyScores);
+        }
+        let noisy_scores: Vec<(usize, F)> = scores
+            .iter()
+            .enumerate()
+            .map(|(i, &score)| (i, score.float_add(cast(noise.sample(rng)))))
+            .collect();
+
+        GumbelTopBucket {
+            scores_len: noisy_scores.len(),
+            scores: scores
+                .iter()
+                .map(|&score| score.float_add(F::zero()))
+                .collect(),
+            temperature: F::nan(),
+            noisy_scores,
+            cursor: 0,
         }
-        let (idx_max, noisy_score) = self.noisy_scores.remove(0);
-        self.scores_len -= 1;
-        Some((idx_max, noisy_score))
     }
 
-    /// Draws a score from the bucket. This returns the index of the score in the original list.
-    /// The score index will be removed from the list and never sampled again. The method will
-    /// return None if the bucket is empty.
-    pub fn draw(&mut self) -> Option<usize> {
-        let (idx_max, _) = self.draw_with_score()?;
-        Some(idx_max)
+    /// Create a new GumbelTopBucket from a slice of scores and a slice of precomputed noise,
+    /// bypassing the random number generator entirely. Each noisy score is
+    /// `scores[i].float_add(noise[i])`, with no temperature scaling, which makes the draw
+    /// order fully deterministic. This is useful for testing, and for noise that was generated
+    /// elsewhere (e.g. on a GPU). As with `new_with_noise`, the bucket records no temperature.
+    /// This panics if `noise` does not have the same length as `scores`, or if the scores are
+    /// empty.
+    pub fn new_with_noise_slice<T>(scores: &[T], noise: &[F]) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        assert_eq!(
+            scores.len(),
+            noise.len(),
+            "scores and noise must have the same length"
+        );
+        if scores.is_empty() {
+            panic!("{}", GumbelError::EmptyScores);
+        }
+        let mut noisy_scores: Vec<(usize, F)> = scores
+            .iter()
+            .zip(noise)
+            .enumerate()
+            .map(|(i, (&score, &noise))| (i, score.float_add(noise)))
+            .collect();
+        Self::sort_noisy_scores(&mut noisy_scores);
+
+        GumbelTopBucket {
+            scores_len: noisy_scores.len(),
+            scores: scores
+                .iter()
+                .map(|&score| score.float_add(F::zero()))
+                .collect(),
+            temperature: F::nan(),
+            noisy_scores,
+            cursor: 0,
+        }
     }
-}
+
+    /// Create a new GumbelTopBucket from the softmax of the scores, rather than from the raw
+    /// scores. The softmax is computed in a numerically stable way (by subtracting the maximum
+    /// score before exponentiating), and the resulting probabilities are then perturbed with
+    /// Gumbel noise of the given temperature, as in `new`. This maps arbitrary real-valued
+    /// scores into the recommended [0, 1] range, but note that it changes the sampling
+    /// distribution compared to building the bucket from the raw scores with `new`.
+    /// `original_score` returns the probabilities, not the raw scores. This panics if the
+    /// scores are empty, any of them is not finite, or the temperature is invalid.
+    #[cfg(feature = "std")]
+    pub fn new_normalized<T>(scores: &[T], temperature: F) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        Self::new_normalized_with_rng(scores, temperature, &mut rand::thread_rng())
+    }
+
+    /// Create a new GumbelTopBucket like `new_normalized`, but drawing the Gumbel noise from
+    /// the given random number generator.
+    pub fn new_normalized_with_rng<T, R>(
+        scores: &[T],
+        temperature: F,
+        rng: &mut R,
+    ) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+        R: Rng + ?Sized,
+    {
+        Self::assert_valid(scores.len(), temperature);
+        // an infinite score would make the softmax NaN.
+        if let Some(idx) = scores
+            .iter()
+            .position(|&score| !score.float_add(F::zero()).is_finite())
+        {
+            panic!("{}", GumbelError::NonFiniteScore(idx));
+        }
+        let max = scores.iter().fold(F::neg_infinity(), |max, &score| {
+            max.max(score.float_add(F::zero()))
+        });
+        let exps: Vec<F> = scores
+            .iter()
+            .map(|&score| (score.float_add(F::zero()) - max).exp())
+            .collect();
+        let total = exps.iter().fold(F::zero(), |total, &exp| total + exp);
+        Self::build(
+            exps.iter().map(|&exp| Native(exp / total)),
+            iter::repeat(temperature),
+            temperature,
+            rng,
+        )
+    }
+
+    /// Create a new GumbelTopBucket from unnormalized log-probabilities (logits). This follows
+    /// the textbook Gumbel-max formulation: the logits are divided by the temperature and
+    /// standard Gumbel(0, 1) noise is added, so the first draw is `argmax(logit_i / t + g_i)`,
+    /// which is distributed as `softmax(logits / t)`. This yields the same distribution as
+    /// `new`, which scales the noise instead, but the noisy scores are on the logit scale.
+    /// `original_score` still returns the unscaled logits. This panics if the logits are
+    /// empty or the temperature is invalid.
+    #[cfg(feature = "std")]
+    pub fn new_from_logits<T>(logits: &[T], temperature: F) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        Self::new_from_logits_with_rng(logits, temperature, &mut rand::thread_rng())
+    }
+
+    /// Create a new GumbelTopBucket like `new_from_logits`, but drawing the Gumbel noise from
+    /// the given random number generator. See `new_with_rng`.
+    pub fn new_from_logits_with_rng<T, R>(
+        logits: &[T],
+        temperature: F,
+        rng: &mut R,
+    ) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+        R: Rng + ?Sized,
+    {
+        Self::assert_valid(logits.len(), temperature);
+        let between = uniform();
+        let mut noisy_scores: Vec<(usize, F)> = Vec::with_capacity(logits.len());
+        let mut scores: Vec<F> = Vec::with_capacity(logits.len());
+        for (i, &logit) in logits.iter().enumerate() {
+            let logit = logit.float_add(F::zero());
+            noisy_scores.push((
+                i,
+                // at a temperature of 0 the noise vanishes, and so does the division.
+                if temperature.is_zero() {
+                    logit
+                } else {
+                    logit / temperature + gumbel_as(between.sample(rng), F::one())
+                },
+            ));
+            scores.push(logit);
+        }
+        Self::sort_noisy_scores(&mut noisy_scores);
+
+        GumbelTopBucket {
+            scores_len: noisy_scores.len(),
+            scores,
+            temperature,
+            noisy_scores,
+            cursor: 0,
+        }
+    }
+
+    /// Create a new GumbelTopBucket like `new`, but only keep the `k` highest noisy scores.
+    /// Instead of sorting all of the `n` noisy scores, the top `k` are selected in O(n) and
+    /// only those are sorted, for a total of O(n + k log k), which is much faster when
+    /// `k` is small compared to `n`. The bucket only ever yields these `k` scores: once they
+    /// have been drawn, `draw` returns None even though the other scores were never drawn.
+    /// Likewise, `reset_noise` only re-rolls the noise of the `k` kept scores.
+    #[cfg(feature = "std")]
+    pub fn new_top_k<T>(scores: &[T], temperature: F, k: usize) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        Self::new_top_k_with_rng(scores, temperature, k, &mut rand::thread_rng())
+    }
+
+    /// Create a new GumbelTopBucket like `new_top_k`, but drawing the Gumbel noise from the
+    /// given random number generator. See `new_with_rng`.
+    pub fn new_top_k_with_rng<T, R>(
+        scores: &[T],
+        temperature: F,
+        k: usize,
+        rng: &mut R,
+    ) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+        R: Rng + ?Sized,
+    {
+        Self::assert_valid(scores.len(), temperature);
+        let mut bucket = Self::perturb(
+            scores.iter().copied(),
+            iter::repeat(temperature),
+            temperature,
+            rng,
+        );
+        bucket.keep_top_k(k);
+        bucket
+    }
+
+    /// Create a new GumbelTopBucket like `new`, but only keep the `k` *lowest* noisy scores,
+    /// e.g. to sample unlikely items as negatives for contrastive training. The scores are
+    /// streamed through a bounded max-heap of the `k` lowest noisy scores seen so far, which
+    /// takes O(n log k) without sorting or storing the other noisy scores. The kept scores
+    /// are drawn in the usual order, from the highest of them; `draw_min` draws them from the
+    /// lowest instead. Like with `new_top_k`, drawing beyond `k` items is not supported: once
+    /// the `k` scores have been drawn, `draw` returns None, and `reset_noise` only re-rolls the
+    /// noise of the kept scores. Note that this is not the same as sampling from the softmax
+    /// of the negated scores, which `new_top_k` does when given negated scores.
+    #[cfg(feature = "std")]
+    pub fn new_bottom_k<T>(scores: &[T], temperature: F, k: usize) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        Self::new_bottom_k_with_rng(scores, temperature, k, &mut rand::thread_rng())
+    }
+
+    /// Create a new GumbelTopBucket like `new_bottom_k`, but drawing the Gumbel noise from the
+    /// given random number generator. See `new_with_rng`.
+    pub fn new_bottom_k_with_rng<T, R>(
+        scores: &[T],
+        temperature: F,
+        k: usize,
+        rng: &mut R,
+    ) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+        R: Rng + ?Sized,
+    {
+        Self::assert_valid(scores.len(), temperature);
+        let between = uniform();
+        let mut lowest: BinaryHeap<DrawOrder<F>> = BinaryHeap::with_capacity(k.min(scores.len()));
+        for (i, &score) in scores.iter().enumerate() {
+            let entry = DrawOrder((
+                i,
+                score.float_add(gumbel_as(between.sample(rng), temperature)),
+            ));
+            if lowest.len() < k {
+                lowest.push(entry);
+            } else if let Some(mut highest) = lowest.peek_mut() {
+                if entry < *highest {
+                    *highest = entry;
+                }
+            }
+        }
+        let mut noisy_scores: Vec<(usize, F)> =
+            lowest.into_iter().map(|DrawOrder(entry)| entry).collect();
+        Self::sort_noisy_scores(&mut noisy_scores);
+
+        GumbelTopBucket {
+            scores_len: noisy_scores.len(),
+            scores: scores
+                .iter()
+                .map(|&score| score.float_add(F::zero()))
+                .collect(),
+            temperature,
+            noisy_scores,
+            cursor: 0,
+        }
+    }
+
+    /// Create a new GumbelTopBucket that only holds the highest noisy score, for when a single
+    /// index is ever drawn from each bucket. The argmax of the noisy scores is found in one
+    /// O(n) pass, without sorting or even storing the other noisy scores, which makes this
+    /// much faster than `new` for the "sample one item" case. The first `draw` returns the
+    /// same index `new` would have; every draw after it returns None, so `draw` yields at
+    /// most one item. Like `new_top_k` with a `k` of 1, `reset_noise` only re-rolls the noise
+    /// of the kept score.
+    #[cfg(feature = "std")]
+    pub fn new_single<T>(scores: &[T], temperature: F) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        Self::new_single_with_rng(scores, temperature, &mut rand::thread_rng())
+    }
+
+    /// Create a new GumbelTopBucket like `new_single`, but drawing the Gumbel noise from the
+    /// given random number generator.
+    pub fn new_single_with_rng<T, R>(
+        scores: &[T],
+        temperature: F,
+        rng: &mut R,
+    ) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+        R: Rng + ?Sized,
+    {
+        Self::assert_valid(scores.len(), temperature);
+        let between = uniform();
+        let mut originals = Vec::with_capacity(scores.len());
+        let mut best: Option<(usize, F)> = None;
+        for (i, &score) in scores.iter().enumerate() {
+            let entry = (
+                i,
+                score.float_add(gumbel_as(between.sample(rng), temperature)),
+            );
+            best = match best {
+                Some(best) if cmp_entries(&best, &entry) != Ordering::Greater => Some(best),
+                _ => Some(entry),
+            };
+            originals.push(score.float_add(F::zero()));
+        }
+
+        GumbelTopBucket {
+            scores_len: 1,
+            scores: originals,
+            temperature,
+            noisy_scores: best.into_iter().collect(),
+            cursor: 0,
+        }
+    }
+
+    /// Create a new GumbelTopBucket from scores that are already sorted in descending order,
+    /// adding Gumbel noise to them but skipping the sort. The bucket trusts the caller's
+    /// ordering, and draws the scores in input order, whatever their noisy scores are. This is
+    /// a performance escape hatch for when the sort is the bottleneck and the gaps between the
+    /// scores are large enough that the noise would hardly reorder them anyway. No index is
+    /// ever drawn twice, but the draw order does not follow the noisy scores: it is only
+    /// distributed like the one of `new` in the limit of a tiny temperature. Methods that
+    /// insert into the sorted order, such as `push`, `reinsert` or `merge`, place scores
+    /// sensibly only if the noisy scores happen to be sorted. Like `new`, this panics if the
+    /// scores are empty or the temperature is invalid.
+    #[cfg(feature = "std")]
+    pub fn new_presorted_unchecked<T>(sorted_scores: &[T], temperature: F) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        Self::new_presorted_unchecked_with_rng(sorted_scores, temperature, &mut rand::thread_rng())
+    }
+
+    /// Create a new GumbelTopBucket like `new_presorted_unchecked`, but drawing the Gumbel
+    /// noise from the given random number generator.
+    pub fn new_presorted_unchecked_with_rng<T, R>(
+        sorted_scores: &[T],
+        temperature: F,
+        rng: &mut R,
+    ) -> GumbelTopBucket<F>
+    where
+        T: FloatAdd<F> + Copy,
+        R: Rng + ?Sized,
+    {
+        Self::assert_valid(sorted_scores.len(), temperature);
+        Self::perturb(
+            sorted_scores.iter().copied(),
+            iter::repeat(temperature),
+            temperature,
+            rng,
+        )
+    }
+
+    /// Keeps only the `k` highest noisy scores of a freshly perturbed bucket, which do not
+    /// need to be sorted yet, and sorts them.
+    fn keep_top_k(&mut self, k: usize) {
+        if k < self.noisy_scores.len() {
+            self.noisy_scores.select_nth_unstable_by(k, cmp_entries);
+            self.noisy_scores.truncate(k);
+            self.noisy_scores.shrink_to_fit();
+            self.scores_len = k;
+        }
+        Self::sort_noisy_scores(&mut self.noisy_scores);
+    }
+
+    /// Builds the bucket without validating the scores or the temperatures, scaling the
+    /// noise of each score by the temperature it is zipped with. The original index of each
+    /// score is its position in the iteration order. `temperature` is the single temperature
+    /// recorded for the bucket, NaN if the scores have different ones.
+    fn build<I, T, J, R>(
+        scores: I,
+        temperatures: J,
+        temperature: F,
+        rng: &mut R,
+    ) -> GumbelTopBucket<F>
+    where
+        I: IntoIterator<Item = T>,
+        T: FloatAdd<F> + Copy,
+        J: IntoIterator<Item = F>,
+        R: Rng + ?Sized,
+    {
+        let mut bucket = Self::perturb(scores, temperatures, temperature, rng);
+        Self::sort_noisy_scores(&mut bucket.noisy_scores);
+        bucket
+    }
+
+    /// Like `build`, but leaves the noisy scores unsorted, for constructors that order
+    /// them differently.
+    fn perturb<I, T, J, R>(
+        scores: I,
+        temperatures: J,
+        temperature: F,
+        rng: &mut R,
+    ) -> GumbelTopBucket<F>
+    where
+        I: IntoIterator<Item = T>,
+        T: FloatAdd<F> + Copy,
+        J: IntoIterator<Item = F>,
+        R: Rng + ?Sized,
+    {
+        let scores = scores.into_iter();
+        let mut bucket = GumbelTopBucket {
+            scores_len: 0,
+            scores: Vec::with_capacity(scores.size_hint().0),
+            temperature,
+            noisy_scores: Vec::with_capacity(scores.size_hint().0),
+            cursor: 0,
+        };
+        bucket.refill(scores, temperatures, rng);
+        bucket
+    }
+
+    /// Replaces the contents of the bucket with the given scores and fresh, unsorted noisy
+    /// scores, reusing the existing allocations.
+    fn refill<I, T, J, R>(&mut self, scores: I, temperatures: J, rng: &mut R)
+    where
+        I: IntoIterator<Item = T>,
+        T: FloatAdd<F> + Copy,
+        J: IntoIterator<Item = F>,
+        R: Rng + ?Sized,
+    {
+        let between = uniform();
+        self.scores.clear();
+        self.noisy_scores.clear();
+        for (i, (score, temperature)) in scores.into_iter().zip(temperatures).enumerate() {
+            let noise = gumbel_as(between.sample(rng), temperature);
+            self.noisy_scores.push((i, score.float_add(noise)));
+            self.scores.push(score.float_add(F::zero()));
+        }
+        self.cursor = 0;
+        self.scores_len = self.noisy_scores.len();
+    }
+
+    /// Panics with the corresponding `GumbelError` if there are no scores or the temperature
+    /// is invalid. This is the validation the panicking constructors share.
+    fn assert_valid(len: usize, temperature: F) {
+        if let Err(err) = Self::validate_temperature(temperature) {
+            panic!("{}", err);
+        }
+        if len == 0 {
+            panic!("{}", GumbelError::EmptyScores);
+        }
+    }
+
+    /// Checks that the temperature is finite and not negative.
+    fn validate_temperature(temperature: F) -> Result<(), GumbelError> {
+        if !(temperature.is_finite() && temperature >= F::zero()) {
+            return Err(GumbelError::NonPositiveTemperature);
+        }
+        Ok(())
+    }
+
+    /// Checks that the scores are non-empty, finite and exactly representable.
+    fn validate_scores<T>(scores: &[T]) -> Result<(), GumbelError>
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        if scores.is_empty() {
+            return Err(GumbelError::EmptyScores);
+        }
+        if let Some(idx) = scores.iter().position(|score| !score.is_exact()) {
+            return Err(GumbelError::InexactScore(idx));
+        }
+        match scores
+            .iter()
+            .position(|&score| !score.float_add(F::zero()).is_finite())
+        {
+            Some(idx) => Err(GumbelError::NonFiniteScore(idx)),
+            None => Ok(()),
+        }
+    }
+
+    /// Sorts the noisy scores in descending order, such that the next score to draw is
+    /// always at the front. NaN scores are sorted to the very end, and equal scores by index.
+    /// Tiny slices, which are common for binary choices, skip the general sort machinery.
+    /// Like `draw_order_key`, it is only public for the benches.
+    #[doc(hidden)]
+    pub fn sort_noisy_scores(noisy_scores: &mut [(usize, F)]) {
+        match noisy_scores {
+            [] | [_] => {}
+            [a, b] => {
+                if cmp_entries(a, b) == Ordering::Greater {
+                    core::mem::swap(a, b);
+                }
+            }
+            // sorting by an integer key is about twice as fast for large buckets as calling
+            // `cmp_entries` on every comparison, and yields the same order.
+            _ => noisy_scores
+                .sort_unstable_by_key(|&(idx, noisy_score)| (draw_order_key(noisy_score), idx)),
+        }
+    }
+
+    /// Re-roll the Gumbel noise of the bucket with the given temperature, making every
+    /// score drawable again. This is equivalent to building a new bucket from the same
+    /// scores, but it reuses the existing allocation, which makes it much cheaper when
+    /// the same scores are sampled from over and over again.
+    #[cfg(feature = "std")]
+    pub fn reset_noise(&mut self, temperature: F) {
+        self.reset_noise_with_rng(temperature, &mut rand::thread_rng());
+    }
+
+    /// Re-roll the Gumbel noise of the bucket like `reset_noise`, but using the given
+    /// random number generator.
+    pub fn reset_noise_with_rng<R>(&mut self, temperature: F, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        let between = uniform();
+        for (idx, noisy_score) in self.noisy_scores.iter_mut() {
+            *noisy_score = self.scores[*idx] + gumbel_as(between.sample(rng), temperature);
+        }
+        Self::sort_noisy_scores(&mut self.noisy_scores);
+        self.temperature = temperature;
+        self.cursor = 0;
+        self.scores_len = self.noisy_scores.len();
+    }
+
+    /// Generate a vector of Gumbel noise. This is used internally to generate the
+    /// noisy scores. It is exposed as a public function in case you want to use
+    /// the Gumbel noise for something else.
+    #[cfg(feature = "std")]
+    pub fn gumbel_noise(size: usize, temperature: F) -> Vec<F> {
+        Self::gumbel_noise_with_rng(size, temperature, &mut rand::thread_rng())
+    }
+
+    /// Generate a vector of Gumbel noise like `gumbel_noise`, but using the given
+    /// random number generator. This is the seedable counterpart of `gumbel_noise`.
+    pub fn gumbel_noise_with_rng<R>(size: usize, temperature: F, rng: &mut R) -> Vec<F>
+    where
+        R: Rng + ?Sized,
+    {
+        Self::gumbel_noise_loc_with_rng(size, F::zero(), temperature, rng)
+    }
+
+    /// Generate a vector of Gumbel noise with the given location μ and scale β, i.e.
+    /// `location - scale * ln(-ln(u))` for uniform samples `u`. `gumbel_noise` is the special
+    /// case of a location of 0, with the temperature as the scale.
+    #[cfg(feature = "std")]
+    pub fn gumbel_noise_loc(size: usize, location: F, scale: F) -> Vec<F> {
+        Self::gumbel_noise_loc_with_rng(size, location, scale, &mut rand::thread_rng())
+    }
+
+    /// Generate a vector of Gumbel noise like `gumbel_noise_loc`, but using the given random
+    /// number generator.
+    pub fn gumbel_noise_loc_with_rng<R>(size: usize, location: F, scale: F, rng: &mut R) -> Vec<F>
+    where
+        R: Rng + ?Sized,
+    {
+        Self::gumbel_noise_iter(rng, scale)
+            .take(size)
+            .map(|noise| location + noise)
+            .collect()
+    }
+
+    /// Generate a vector of Gumbel noise like `gumbel_noise`, but clamping the uniform samples
+    /// the noise is derived from to `[eps, 1 - eps)` instead of `UNIFORM_EPS`. The clamp keeps
+    /// the logarithms finite, but also cuts off both tails of the distribution: at a
+    /// temperature of 1, the noise never goes below `-ln(-ln(eps))` nor above about
+    /// `-ln(eps)`, which are each exceeded with a probability of about `eps`. A smaller `eps`
+    /// reduces this bias for very large sample counts, down to the smallest positive f64.
+    /// This panics if `eps` is not in `(0, 0.5)`.
+    #[cfg(feature = "std")]
+    pub fn gumbel_noise_eps(size: usize, temperature: F, eps: f64) -> Vec<F> {
+        Self::gumbel_noise_eps_with_rng(size, temperature, eps, &mut rand::thread_rng())
+    }
+
+    /// Generate a vector of Gumbel noise like `gumbel_noise_eps`, but using the given random
+    /// number generator.
+    pub fn gumbel_noise_eps_with_rng<R>(
+        size: usize,
+        temperature: F,
+        eps: f64,
+        rng: &mut R,
+    ) -> Vec<F>
+    where
+        R: Rng + ?Sized,
+    {
+        let between = uniform_with_eps(eps);
+        iter::repeat_with(|| gumbel_as(between.sample(rng), temperature))
+            .take(size)
+            .collect()
+    }
+
+    /// Fill `buf` with `size` samples of Gumbel noise, like `gumbel_noise`, but reusing the
+    /// buffer instead of allocating a new one. The buffer is cleared first, so that the same
+    /// buffer can be recycled across iterations.
+    #[cfg(feature = "std")]
+    pub fn gumbel_noise_into(buf: &mut Vec<F>, size: usize, temperature: F) {
+        Self::gumbel_noise_into_with_rng(buf, size, temperature, &mut rand::thread_rng())
+    }
+
+    /// Fill `buf` with Gumbel noise like `gumbel_noise_into`, but using the given random
+    /// number generator.
+    pub fn gumbel_noise_into_with_rng<R>(buf: &mut Vec<F>, size: usize, temperature: F, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        buf.clear();
+        buf.reserve(size);
+        buf.extend(Self::gumbel_noise_iter(rng, temperature).take(size));
+    }
+
+    /// Transform the given uniform samples into Gumbel noise, as `-ln(-ln(u)) * temperature`
+    /// for each `u`, without using any random number generator. This is the transform that
+    /// `gumbel_noise` applies to the uniform samples it draws, so it can be tested in
+    /// isolation, or fed with quasi-random sequences such as Sobol points. This panics if
+    /// one of the samples is not strictly between 0 and 1.
+    pub fn gumbel_from_uniforms(uniforms: &[f64], temperature: F) -> Vec<F> {
+        uniforms
+            .iter()
+            .map(|&u| {
+                assert!(u > 0.0 && u < 1.0, "uniform sample {} is not in (0, 1)", u);
+                gumbel_as(u, temperature)
+            })
+            .collect()
+    }
+
+    /// Returns an endless iterator of Gumbel noise drawn from the given random number
+    /// generator, like `gumbel_noise` but lazily, without materializing a vector. This allows
+    /// zipping the noise with scores on the fly, and taking exactly as many samples as needed.
+    pub fn gumbel_noise_iter<'a, R>(rng: &'a mut R, temperature: F) -> impl Iterator<Item = F> + 'a
+    where
+        R: Rng + ?Sized,
+        F: 'a,
+    {
+        let between = uniform();
+        iter::repeat_with(move || gumbel_as(between.sample(rng), temperature))
+    }
+
+    /// Draw a score from the bucket. This returns the index of the score in the original list,
+    /// as well as the *noisy* score. The score index will be removed from the list and never
+    /// sampled again. The method will return None if the bucket is empty. Drawing is O(1),
+    /// as the noisy scores are sorted once on construction and a cursor is advanced over them.
+    #[must_use]
+    pub fn draw_with_score(&mut self) -> Option<(usize, F)> {
+        if self.scores_len == 0 {
+            return None;
+        }
+        let (idx_max, noisy_score) = self.noisy_scores[self.cursor];
+        self.cursor += 1;
+        self.scores_len -= 1;
+        Some((idx_max, noisy_score))
+    }
+
+    /// Draw a score from the bucket like `draw_with_score`, but without checking whether the
+    /// bucket is empty. This is an escape hatch for hot loops that already know how many scores
+    /// remain, in the same spirit as `slice::get_unchecked`; `draw_with_score` should be
+    /// preferred everywhere else.
+    ///
+    /// # Safety
+    ///
+    /// The bucket must not be empty, i.e. `remaining()` must be greater than 0. Calling this on
+    /// an empty bucket is undefined behavior.
+    pub unsafe fn draw_unchecked(&mut self) -> (usize, F) {
+        debug_assert!(self.scores_len > 0, "draw_unchecked on an empty bucket");
+        // SAFETY: the caller guarantees that the bucket is not empty, so the cursor is within
+        // the bounds of `noisy_scores`.
+        let entry = unsafe { *self.noisy_scores.get_unchecked(self.cursor) };
+        self.cursor += 1;
+        self.scores_len -= 1;
+        entry
+    }
+
+    /// Draws a score from the bucket. This returns the index of the score in the original list.
+    /// The score index will be removed from the list and never sampled again. The method will
+    /// return None if the bucket is empty.
+    #[must_use]
+    pub fn draw(&mut self) -> Option<usize> {
+        let (idx_max, _) = self.draw_with_score()?;
+        Some(idx_max)
+    }
+
+    /// Draw the *lowest* remaining noisy score from the bucket, i.e. the one that `draw` would
+    /// return last. This returns the index of the score in the original list, as well as the
+    /// noisy score, and removes it from the bucket like `draw_with_score`. Interleaving `draw`
+    /// and `draw_min` draws from both ends of the same sorted order, so the two never collide:
+    /// every index is drawn at most once, and they meet once the bucket is empty. The method
+    /// will return None if the bucket is empty.
+    #[must_use]
+    pub fn draw_min_with_score(&mut self) -> Option<(usize, F)> {
+        if self.scores_len == 0 {
+            return None;
+        }
+        self.scores_len -= 1;
+        Some(self.noisy_scores[self.cursor + self.scores_len])
+    }
+
+    /// Draw the lowest remaining noisy score from the bucket, returning its index in the
+    /// original list. See `draw_min_with_score`.
+    #[must_use]
+    pub fn draw_min(&mut self) -> Option<usize> {
+        let (idx_min, _) = self.draw_min_with_score()?;
+        Some(idx_min)
+    }
+
+    /// Draw a score from the bucket like `draw_with_score`, but returning the *original*,
+    /// pre-noise score of the drawn index instead of its noisy score.
+    pub fn draw_with_original(&mut self) -> Option<(usize, F)> {
+        let (idx_max, _) = self.draw_with_score()?;
+        Some((idx_max, self.scores[idx_max]))
+    }
+
+    /// Draw a score from the bucket like `draw`, also returning the probability with which it
+    /// was drawn, conditional on the draws made before it: the softmax of the original scores
+    /// over the indices that were not drawn before, `exp(s_i / t) / sum_j exp(s_j / t)`, where
+    /// `i` is the drawn index, `j` goes over every undrawn index of the original list including
+    /// `i`, and `t` is the temperature of the bucket. The product of these probabilities over
+    /// successive draws is the probability of the drawn sequence, as needed by importance
+    /// weights and unbiased estimators on samples without replacement. The sum also covers the
+    /// indices that buckets such as `new_single` or `new_top_k` did not keep, as they could
+    /// have been drawn too, and likewise the indices removed with `remove_index` or `retain`,
+    /// which the bucket does not keep track of. The maximum score is subtracted first, for
+    /// numerical stability; if it is infinite, the probability is 1 for the drawn infinite
+    /// score, as for a temperature of 0. For buckets without a single temperature, see
+    /// `probability`, the probability is NaN. This scans the original scores, so it is O(n)
+    /// instead of O(1).
+    pub fn draw_with_probability(&mut self) -> Option<(usize, F)> {
+        let score = self.scores[self.peek()?];
+        let temperature = self.temperature;
+        let probability = if temperature.is_zero() || score == F::infinity() {
+            F::one()
+        } else {
+            let mut undrawn = alloc::vec![true; self.scores.len()];
+            let drawn = &self.noisy_scores[..self.cursor];
+            for &(idx, _) in drawn.iter().chain(&self.noisy_scores[self.end()..]) {
+                undrawn[idx] = false;
+            }
+            let undrawn = || {
+                self.scores
+                    .iter()
+                    .zip(&undrawn)
+                    .filter(|&(_, &undrawn)| undrawn)
+                    .map(|(&score, _)| score)
+            };
+            let max = undrawn().fold(F::neg_infinity(), |max, score| max.max(score));
+            let total = undrawn().fold(F::zero(), |total, score| {
+                total + ((score - max) / temperature).exp()
+            });
+            ((score - max) / temperature).exp() / total
+        };
+        let idx = self.draw()?;
+        Some((idx, probability))
+    }
+
+    /// Draw a score from the bucket like `draw`, also returning where the *original* score of
+    /// the drawn index sits among the original scores of the indices that were remaining
+    /// before the draw, including itself: 0 if it is the lowest of them, and 1 if it is the
+    /// highest. The fraction is the number of other remaining indices with a lower original
+    /// score, divided by the number of other remaining indices, so tied scores share the
+    /// lowest of their ranks, and NaN scores are never counted as lower. When the drawn index
+    /// was the only one left, the fraction is defined as 1. This scans the remaining scores,
+    /// so it is O(n) instead of O(1).
+    pub fn draw_with_rank_fraction(&mut self) -> Option<(usize, F)> {
+        let score = self.scores[self.peek()?];
+        let lower = self
+            .as_slice()
+            .iter()
+            .filter(|&&(idx, _)| self.scores[idx] < score)
+            .count();
+        let others = self.scores_len - 1;
+        let idx = self.draw()?;
+        if others == 0 {
+            return Some((idx, F::one()));
+        }
+        Some((idx, cast::<F>(lower as f64 / others as f64)))
+    }
+
+    /// Draw up to `n` scores from the bucket at once. This returns the indices of the scores
+    /// in the original list together with their *noisy* scores, ordered by descending noisy
+    /// score, exactly as `n` repeated calls to `draw_with_score` would yield them. If fewer
+    /// than `n` scores remain, all of the remaining ones are returned.
+    pub fn draw_n_with_scores(&mut self, n: usize) -> Vec<(usize, F)> {
+        let n = n.min(self.scores_len);
+        let drawn = self.noisy_scores[self.cursor..self.cursor + n].to_vec();
+        self.cursor += n;
+        self.scores_len -= n;
+        drawn
+    }
+
+    /// Draw up to `n` scores from the bucket at once, returning only the indices of the
+    /// scores in the original list. See `draw_n_with_scores` for the ordering guarantees.
+    pub fn draw_n(&mut self, n: usize) -> Vec<usize> {
+        let n = n.min(self.scores_len);
+        let drawn = self.noisy_scores[self.cursor..self.cursor + n]
+            .iter()
+            .map(|&(idx, _)| idx)
+            .collect();
+        self.cursor += n;
+        self.scores_len -= n;
+        drawn
+    }
+
+    /// Draw up to `n` scores from the bucket like `draw_n`, but appending the indices to
+    /// `buf` instead of allocating a new vector, so that the same buffer can be recycled
+    /// across iterations. The buffer is not cleared first. Returns the number of indices that
+    /// were appended, which is less than `n` if fewer scores remain.
+    pub fn draw_n_into(&mut self, buf: &mut Vec<usize>, n: usize) -> usize {
+        let n = n.min(self.scores_len);
+        buf.extend(
+            self.noisy_scores[self.cursor..self.cursor + n]
+                .iter()
+                .map(|&(idx, _)| idx),
+        );
+        self.cursor += n;
+        self.scores_len -= n;
+        n
+    }
+
+    /// Draw a batch of up to `n` indices from the bucket. This is the same as `draw_n`, but is
+    /// the batch counterpart of multinomial sampling *without* replacement: each index is
+    /// drawn from the softmax of the scores that are still remaining, so the batch has no
+    /// duplicates and its composition is the one of `n` successive softmax draws, each
+    /// renormalized after removing the previous ones. This differs from `n` independent
+    /// multinomial draws, where the high-scoring indices can be drawn several times; in
+    /// particular, every index is in a batch of `n == remaining()` indices with probability 1.
+    pub fn draw_budget(&mut self, n: usize) -> Vec<usize> {
+        self.draw_n(n)
+    }
+
+    /// Look at the score that the next call to `draw_with_score` would return, without
+    /// removing it from the bucket. This returns the index of the score in the original list,
+    /// as well as the *noisy* score, or None if the bucket is empty.
+    #[must_use]
+    pub fn peek_with_score(&self) -> Option<(usize, F)> {
+        if self.scores_len == 0 {
+            return None;
+        }
+        Some(self.noisy_scores[self.cursor])
+    }
+
+    /// Look at the index that the next call to `draw` would return, without removing it
+    /// from the bucket. The method will return None if the bucket is empty.
+    #[must_use]
+    pub fn peek(&self) -> Option<usize> {
+        let (idx_max, _) = self.peek_with_score()?;
+        Some(idx_max)
+    }
+
+    /// Look at up to `k` scores that the next calls to `draw_with_score` would return, without
+    /// removing them from the bucket. The indices and *noisy* scores are returned in exactly
+    /// the order `draw_n_with_scores(k)` would return them, so they can be used to plan ahead
+    /// before committing to any draw.
+    pub fn peek_n_with_scores(&self, k: usize) -> Vec<(usize, F)> {
+        let k = k.min(self.scores_len);
+        self.noisy_scores[self.cursor..self.cursor + k].to_vec()
+    }
+
+    /// Look at up to `k` indices that the next calls to `draw` would return, without removing
+    /// them from the bucket. See `peek_n_with_scores` for the ordering guarantees.
+    pub fn peek_n(&self, k: usize) -> Vec<usize> {
+        let k = k.min(self.scores_len);
+        self.noisy_scores[self.cursor..self.cursor + k]
+            .iter()
+            .map(|&(idx, _)| idx)
+            .collect()
+    }
+
+    /// Returns the number of scores that can still be drawn from the bucket.
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.scores_len
+    }
+
+    /// Returns true if there are no scores left to draw from the bucket.
+    pub fn is_empty(&self) -> bool {
+        self.scores_len == 0
+    }
+
+    /// Returns the number of scores in the original list, regardless of how many of them
+    /// have been drawn; `original_len() - remaining()` is the number of scores that are no
+    /// longer drawable. This is O(1). Every index in the original list is below this length,
+    /// including the ones added by `push` and `merge`. For a bucket built with `new_top_k`,
+    /// this is the length of the whole slice of scores, not `k`.
+    pub fn original_len(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// Returns the sum of the original scores of every index in the original list, whether it
+    /// was drawn or not. This is O(n), as no running sum is kept that every draw would have to
+    /// update; infinite or NaN scores make the sum infinite or NaN.
+    pub fn total_weight(&self) -> F {
+        self.scores
+            .iter()
+            .fold(F::zero(), |total, &score| total + score)
+    }
+
+    /// Returns the sum of the original scores of the indices that can still be drawn, e.g. to
+    /// normalize the distribution of the future draws. Like `total_weight`, this is O(n) in
+    /// the number of remaining scores.
+    pub fn remaining_weight(&self) -> F {
+        self.as_slice()
+            .iter()
+            .fold(F::zero(), |total, &(idx, _)| total + self.scores[idx])
+    }
+
+    /// Returns the approximate number of bytes the bucket uses on the heap, i.e. the allocated
+    /// capacity of both the noisy scores and the original scores. Drawing does not free any
+    /// memory, so this stays the same as the bucket is drawn from; `new_top_k` buckets only
+    /// pay for the `k` noisy scores they keep.
+    pub fn memory_footprint(&self) -> usize {
+        self.noisy_scores.capacity() * core::mem::size_of::<(usize, F)>()
+            + self.scores.capacity() * core::mem::size_of::<F>()
+    }
+
+    /// Removes all the remaining scores from the bucket, so that the next draw returns None.
+    /// This is O(1), and keeps the allocated capacity, so the bucket can be refilled through
+    /// `reset_noise` or `rebuild` without reallocating. A cleared bucket is indistinguishable
+    /// from one that was drawn until empty: the original scores are kept, and `reset_noise`
+    /// makes all of them drawable again.
+    pub fn clear(&mut self) {
+        self.cursor = self.end();
+        self.scores_len = 0;
+    }
+
+    /// Returns an iterator that draws from the bucket until it is empty, yielding the
+    /// indices of the scores in the original list. See `draw`.
+    pub fn drain(&mut self) -> Drain<'_, F> {
+        Drain { bucket: self }
+    }
+
+    /// Returns an iterator that draws from the bucket until it is empty, yielding the
+    /// indices of the scores in the original list together with their *noisy* scores.
+    /// See `draw_with_score`.
+    pub fn drain_with_scores(&mut self) -> DrainWithScores<'_, F> {
+        DrainWithScores { bucket: self }
+    }
+
+    /// Consumes the bucket, returning an iterator that draws from it until it is empty,
+    /// yielding the indices of the scores in the original list together with their *noisy*
+    /// scores. This is the owned counterpart of `drain_with_scores`; to only get the indices,
+    /// iterate over the bucket itself.
+    pub fn into_iter_with_scores(self) -> IntoIterWithScores<F> {
+        IntoIterWithScores { bucket: self }
+    }
+
+    /// Returns the original, pre-noise score of the given index in the original list, or
+    /// None if the index is out of bounds. This works regardless of whether the index has
+    /// already been drawn, so the input slice does not need to be kept around.
+    pub fn original_score(&self, idx: usize) -> Option<F> {
+        self.scores.get(idx).copied()
+    }
+
+    /// Put a score back into the bucket so that it can be drawn again, with fresh Gumbel noise
+    /// generated for it at the given temperature. `original_score` replaces the original score
+    /// stored for the index. This deliberately breaks the guarantee that an index is never
+    /// drawn twice, so it is strictly opt-in. Returns false and leaves the bucket unchanged if
+    /// the index is still in the bucket or is out of bounds of the original list.
+    #[cfg(feature = "std")]
+    pub fn reinsert<T>(&mut self, idx: usize, original_score: T, temperature: F) -> bool
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        self.reinsert_with_rng(idx, original_score, temperature, &mut rand::thread_rng())
+    }
+
+    /// Put a score back into the bucket like `reinsert`, but drawing the Gumbel noise from the
+    /// given random number generator.
+    pub fn reinsert_with_rng<T, R>(
+        &mut self,
+        idx: usize,
+        original_score: T,
+        temperature: F,
+        rng: &mut R,
+    ) -> bool
+    where
+        T: FloatAdd<F> + Copy,
+        R: Rng + ?Sized,
+    {
+        if idx >= self.scores.len() || self.position_of(idx).is_some() {
+            return false;
+        }
+        // drop the entry left behind by the earlier draw, so that `reset_noise` does not
+        // bring the index back twice.
+        if let Some(pos) = self.noisy_scores.iter().position(|&(i, _)| i == idx) {
+            self.noisy_scores.remove(pos);
+            if pos < self.cursor {
+                self.cursor -= 1;
+            }
+        }
+        let noise = gumbel_as(uniform().sample(rng), temperature);
+        self.mix_temperature(temperature);
+        self.scores[idx] = original_score.float_add(F::zero());
+        self.insert_sorted(idx, original_score.float_add(noise));
+        true
+    }
+
+    /// Records that some noise was scaled by the given temperature; if it differs from the
+    /// temperature of the rest of the bucket, there is no single temperature anymore.
+    fn mix_temperature(&mut self, temperature: F) {
+        if temperature != self.temperature {
+            self.temperature = F::nan();
+        }
+    }
+
+    /// Returns the position in `noisy_scores` right after the last remaining entry.
+    fn end(&self) -> usize {
+        self.cursor + self.scores_len
+    }
+
+    /// Returns the position in `noisy_scores` of the given index, if it has not been drawn yet.
+    fn position_of(&self, idx: usize) -> Option<usize> {
+        self.as_slice()
+            .iter()
+            .position(|&(i, _)| i == idx)
+            .map(|pos| self.cursor + pos)
+    }
+
+    /// Inserts a noisy score among the remaining ones, keeping them sorted.
+    fn insert_sorted(&mut self, idx: usize, noisy_score: F) {
+        let pos = self.cursor
+            + self
+                .as_slice()
+                .partition_point(|other| cmp_entries(other, &(idx, noisy_score)) == Ordering::Less);
+        self.noisy_scores.insert(pos, (idx, noisy_score));
+        self.scores_len += 1;
+    }
+
+    /// Remove the given index in the original list from the bucket, so that it will never be
+    /// drawn. This is cheaper than rebuilding the bucket just to mask out a single score.
+    /// Returns whether the index was found; if it was already drawn or was never in the
+    /// bucket, this does nothing and returns false.
+    pub fn remove_index(&mut self, idx: usize) -> bool {
+        match self.position_of(idx) {
+            Some(pos) => {
+                self.noisy_scores.remove(pos);
+                self.scores_len -= 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Keep only the remaining scores for which the predicate returns true, like `Vec::retain`.
+    /// The predicate is called once for each remaining score, in draw order, with its index in
+    /// the original list and its *noisy* score. The scores that are kept stay in the same
+    /// order. This is a single O(n) pass, which is cheaper than calling `remove_index` for
+    /// each score to remove.
+    pub fn retain<P>(&mut self, mut pred: P)
+    where
+        P: FnMut(usize, F) -> bool,
+    {
+        let end = self.end();
+        let mut kept = self.cursor;
+        for pos in self.cursor..end {
+            let (idx, noisy_score) = self.noisy_scores[pos];
+            if pred(idx, noisy_score) {
+                self.noisy_scores[kept] = (idx, noisy_score);
+                kept += 1;
+            }
+        }
+        self.noisy_scores.drain(kept..end);
+        self.scores_len = kept - self.cursor;
+    }
+
+    /// Draw a score from the bucket *with* replacement, returning the index in the original
+    /// list of the highest remaining noisy score without removing it. Since the noise is only
+    /// rolled when the bucket is built, repeated calls on the same bucket return the same index;
+    /// to get independent draws with replacement, call `reset_noise` between draws. The method
+    /// will return None if the bucket is empty.
+    pub fn draw_with_replacement(&self) -> Option<usize> {
+        self.peek()
+    }
+
+    /// Returns the remaining `(index, noisy score)` pairs, in the exact order in which future
+    /// calls to `draw_with_score` would yield them, without cloning them. Scores that were
+    /// already drawn are not included.
+    pub fn as_slice(&self) -> &[(usize, F)] {
+        &self.noisy_scores[self.cursor..self.end()]
+    }
+
+    /// Returns the indices in the original list of all the remaining scores, in the exact order
+    /// in which future calls to `draw` would yield them. Scores that were already drawn are
+    /// not included.
+    pub fn sorted_indices(&self) -> Vec<usize> {
+        self.as_slice().iter().map(|&(idx, _)| idx).collect()
+    }
+
+    /// Consumes the bucket, returning the indices of the remaining scores in draw order.
+    /// See `sorted_indices`.
+    pub fn into_sorted_indices(self) -> Vec<usize> {
+        self.noisy_scores
+            .into_iter()
+            .skip(self.cursor)
+            .take(self.scores_len)
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Consumes the bucket, returning the remaining `(index, noisy score)` pairs in draw order,
+    /// like `as_slice`, but by value. The already drawn entries are excluded; they are removed
+    /// in place, so the vector of the bucket is moved out without being cloned.
+    pub fn into_vec(self) -> Vec<(usize, F)> {
+        let mut noisy_scores = self.noisy_scores;
+        noisy_scores.truncate(self.cursor + self.scores_len);
+        noisy_scores.drain(..self.cursor);
+        noisy_scores
+    }
+
+    /// Replace the contents of the bucket with a new slice of scores and a temperature, as if
+    /// it was built from scratch with `new`, but reusing the existing allocations. As long as
+    /// the new scores are no longer than the largest slice the bucket held so far, this does
+    /// not allocate at all, which makes it well suited for rebuilding buckets in a hot loop.
+    /// This panics if the scores are empty or the temperature is invalid.
+    #[cfg(feature = "std")]
+    pub fn rebuild<T>(&mut self, scores: &[T], temperature: F)
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        self.rebuild_with_rng(scores, temperature, &mut rand::thread_rng());
+    }
+
+    /// Replace the contents of the bucket like `rebuild`, but drawing the Gumbel noise from the
+    /// given random number generator.
+    pub fn rebuild_with_rng<T, R>(&mut self, scores: &[T], temperature: F, rng: &mut R)
+    where
+        T: FloatAdd<F> + Copy,
+        R: Rng + ?Sized,
+    {
+        Self::assert_valid(scores.len(), temperature);
+        self.refill(scores.iter().copied(), iter::repeat(temperature), rng);
+        Self::sort_noisy_scores(&mut self.noisy_scores);
+        self.temperature = temperature;
+    }
+
+    /// Returns the theoretical probability that the given index in the original list is the
+    /// first one drawn from a freshly built bucket, which is the softmax over the original
+    /// scores divided by the temperature. This is useful to cross-check empirical draw
+    /// frequencies against the analytical distribution. It does not take into account the
+    /// draws that were already made. As an infinite score is always drawn first, the first
+    /// infinite score gets a probability of 1. Returns None if the index is out of bounds, or
+    /// if the bucket was built with per-element temperatures or a custom noise source, as
+    /// there is no closed form then.
+    pub fn probability(&self, idx: usize) -> Option<F> {
+        let score = *self.scores.get(idx)?;
+        if self.temperature.is_nan() {
+            return None;
+        }
+        let max = self
+            .scores
+            .iter()
+            .fold(F::neg_infinity(), |max, &score| max.max(score));
+        if self.temperature.is_zero() || max == F::infinity() {
+            return Some(self.first_draw_probabilities()?[idx]);
+        }
+        let total: F = self.scores.iter().fold(F::zero(), |total, &score| {
+            total + ((score - max) / self.temperature).exp()
+        });
+        Some(((score - max) / self.temperature).exp() / total)
+    }
+
+    /// Returns the theoretical first-draw probability of every index in the original list, as
+    /// computed by `probability`, in one O(n) pass instead of one per index. The vector is
+    /// aligned with the original list and sums to 1. Like `probability`, this returns None if
+    /// the bucket has no single temperature.
+    pub fn first_draw_probabilities(&self) -> Option<Vec<F>> {
+        if self.temperature.is_nan() {
+            return None;
+        }
+        let max = self
+            .scores
+            .iter()
+            .fold(F::neg_infinity(), |max, &score| max.max(score));
+        // an infinite score is always drawn first, whatever the noise, so the distribution is
+        // the same as without noise.
+        if self.temperature.is_zero() || max == F::infinity() {
+            // the first draw is the highest score, the one with the lowest index if tied.
+            let first = (0..self.scores.len())
+                .map(|idx| (idx, self.scores[idx]))
+                .min_by(cmp_entries)?;
+            let mut probabilities = alloc::vec![F::zero(); self.scores.len()];
+            probabilities[first.0] = F::one();
+            return Some(probabilities);
+        }
+        let mut probabilities: Vec<F> = self
+            .scores
+            .iter()
+            .map(|&score| ((score - max) / self.temperature).exp())
+            .collect();
+        let total = probabilities
+            .iter()
+            .fold(F::zero(), |total, &weight| total + weight);
+        for probability in &mut probabilities {
+            *probability = *probability / total;
+        }
+        Some(probabilities)
+    }
+
+    /// Returns the temperature the Gumbel noise of the bucket was scaled by. Higher
+    /// temperatures flatten the distribution, making the draws closer to uniform, while lower
+    /// temperatures sharpen it, making the draws closer to a sort of the original scores. In
+    /// the limits, a tiny temperature such as `f64::MIN_POSITIVE` makes the noise negligible,
+    /// so the draws follow the descending order of the scores (equal scores in input order),
+    /// and a huge one drowns the scores out, so every order is about equally likely. A
+    /// temperature of exactly 0 removes the noise altogether, so the draws are deterministic.
+    /// This is updated by `reset_noise` and `rebuild`. For buckets built with per-element
+    /// temperatures or a custom noise source there is no single temperature, and this returns
+    /// NaN.
+    pub fn temperature(&self) -> F {
+        self.temperature
+    }
+
+    /// Draw the highest remaining noisy score whose index in the original list satisfies the
+    /// given predicate. The scores before it that were rejected by the predicate are left in
+    /// the bucket, in their original order, so they can still be drawn later. The scan stops
+    /// at the first match. Returns None, leaving the bucket unchanged, if no remaining index
+    /// satisfies the predicate.
+    pub fn draw_filtered<P>(&mut self, mut pred: P) -> Option<usize>
+    where
+        P: FnMut(usize) -> bool,
+    {
+        let pos = self.as_slice().iter().position(|&(idx, _)| pred(idx))?;
+        let (idx, _) = self.take_at(self.cursor + pos);
+        Some(idx)
+    }
+
+    /// Draws the remaining entry at the given position of `noisy_scores`, shifting the entries
+    /// before it back by one so that the remaining ones stay in order.
+    fn take_at(&mut self, pos: usize) -> (usize, F) {
+        self.noisy_scores[self.cursor..=pos].rotate_right(1);
+        self.draw_with_score()
+            .expect("the position should be within the remaining entries")
+    }
+
+    /// Add `delta` to the noisy score of a remaining index in the original list, moving it to
+    /// its new position in the draw order. A negative `delta` makes the index less likely to be
+    /// drawn soon, and a positive one more likely. This allows for online reweighting without
+    /// rebuilding the bucket. Returns whether the index was found; indices that were already
+    /// drawn are not affected.
+    pub fn adjust_score(&mut self, idx: usize, delta: F) -> bool {
+        let pos = match self.position_of(idx) {
+            Some(pos) => pos - self.cursor,
+            None => return false,
+        };
+        let end = self.end();
+        let remaining = &mut self.noisy_scores[self.cursor..end];
+        let old_score = remaining[pos].1;
+        let new_score = old_score + delta;
+        remaining[pos].1 = new_score;
+        match cmp_noisy_scores(new_score, old_score) {
+            Ordering::Less => {
+                let new_pos = remaining[..pos].partition_point(|other| {
+                    cmp_entries(other, &(idx, new_score)) == Ordering::Less
+                });
+                remaining[new_pos..=pos].rotate_right(1);
+            }
+            Ordering::Greater => {
+                let new_pos = pos
+                    + remaining[pos + 1..].partition_point(|other| {
+                        cmp_entries(other, &(idx, new_score)) == Ordering::Less
+                    });
+                remaining[pos..=new_pos].rotate_left(1);
+            }
+            Ordering::Equal => {}
+        }
+        true
+    }
+
+    /// Add a new score to the bucket, turning it into an online structure that can grow while
+    /// it is being drawn from. The score is given fresh Gumbel noise at the given temperature
+    /// and is assigned the next index in the original list, which is returned.
+    #[cfg(feature = "std")]
+    pub fn push<T>(&mut self, score: T, temperature: F) -> usize
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        self.push_with_rng(score, temperature, &mut rand::thread_rng())
+    }
+
+    /// Add a new score to the bucket like `push`, but drawing the Gumbel noise from the given
+    /// random number generator.
+    pub fn push_with_rng<T, R>(&mut self, score: T, temperature: F, rng: &mut R) -> usize
+    where
+        T: FloatAdd<F> + Copy,
+        R: Rng + ?Sized,
+    {
+        let idx = self.scores.len();
+        let noise = gumbel_as(uniform().sample(rng), temperature);
+        self.mix_temperature(temperature);
+        self.scores.push(score.float_add(F::zero()));
+        self.insert_sorted(idx, score.float_add(noise));
+        idx
+    }
+
+    /// Draw a score from the bucket, returning the index of the score in the original list
+    /// together with both its *noisy* and its original score. This saves callers that log
+    /// draws from keeping a lookup table of the original scores. The method will return None
+    /// if the bucket is empty.
+    pub fn draw_full(&mut self) -> Option<DrawResult<F>> {
+        let (index, noisy_score) = self.draw_with_score()?;
+        Some(DrawResult {
+            index,
+            noisy_score,
+            original_score: self.scores[index],
+        })
+    }
+
+    /// Returns true if the given index in the original list has not been drawn yet, and so can
+    /// still be drawn from the bucket. This is O(n) in the number of remaining scores, as it
+    /// scans them; no auxiliary set of drawn indices is kept, to avoid the extra memory.
+    pub fn contains(&self, idx: usize) -> bool {
+        self.position_of(idx).is_some()
+    }
+
+    /// Returns the 0-based rank at which the given index in the original list would be drawn
+    /// among the remaining scores, e.g. `Some(2)` if it is the third item `draw` would return,
+    /// or None if it was already drawn. Like `contains`, this is O(n), as no map from index to
+    /// position is kept.
+    pub fn rank_of(&self, idx: usize) -> Option<usize> {
+        Some(self.position_of(idx)? - self.cursor)
+    }
+
+    /// Keep drawing from the bucket while the given predicate holds, returning the drawn
+    /// indices together with their *noisy* scores. Before each draw, the predicate is called
+    /// with the index that would be drawn next and the sum of the original scores of the
+    /// indices drawn so far by this call. The first index the predicate rejects is *not*
+    /// drawn, and stays at the front of the bucket. With probabilities as scores, this gives
+    /// nucleus (top-p) sampling: `draw_until(|_, cumulative| cumulative < p)`.
+    pub fn draw_until<P>(&mut self, mut pred: P) -> Vec<(usize, F)>
+    where
+        P: FnMut(usize, F) -> bool,
+    {
+        let mut drawn = Vec::new();
+        let mut cumulative = F::zero();
+        while let Some((idx, _)) = self.peek_with_score() {
+            if !pred(idx, cumulative) {
+                break;
+            }
+            let entry = self
+                .draw_with_score()
+                .expect("the bucket should not be empty after a successful peek");
+            cumulative = cumulative + self.scores[idx];
+            drawn.push(entry);
+        }
+        drawn
+    }
+
+    /// Merge the remaining scores of another bucket into this one, so that they can be drawn
+    /// from together. The indices of `other` are renumbered by offsetting them by the length
+    /// of this bucket's original list: index `i` of `other` becomes `n + i`, where `n` is the
+    /// number of scores this bucket was built from, and its original score is appended to
+    /// them accordingly. As both buckets are already sorted, this is a single O(n + m) merge.
+    /// Scores that were already drawn from `other` are not brought over. If the buckets were
+    /// built with different temperatures, the merged bucket has no single temperature anymore.
+    pub fn merge(&mut self, other: GumbelTopBucket<F>) {
+        let offset = self.scores.len();
+        // the entries drawn with `draw_min` are set aside and put back after the merged ones.
+        let drawn_min = self.noisy_scores.split_off(self.end());
+        let mut ours = self
+            .noisy_scores
+            .split_off(self.cursor)
+            .into_iter()
+            .peekable();
+        let mut theirs = other
+            .as_slice()
+            .iter()
+            .map(|&(idx, noisy_score)| (idx + offset, noisy_score))
+            .peekable();
+        self.noisy_scores.reserve(ours.len() + other.scores_len);
+        loop {
+            let entry = match (ours.peek(), theirs.peek()) {
+                (Some(a), Some(b)) if cmp_entries(a, b) == Ordering::Greater => theirs.next(),
+                (Some(_), _) => ours.next(),
+                (None, Some(_)) => theirs.next(),
+                (None, None) => break,
+            };
+            self.noisy_scores.extend(entry);
+        }
+        self.scores_len = self.noisy_scores.len() - self.cursor;
+        self.noisy_scores.extend(drawn_min);
+        self.scores.extend_from_slice(&other.scores);
+        self.mix_temperature(other.temperature);
+    }
+
+    /// Split the draw order of the bucket in two, like `Vec::split_off`: the next `k`
+    /// remaining scores, i.e. the ones the next `k` calls to `draw` would return, are moved
+    /// into a new bucket, in the same order and with the same indices in the original list,
+    /// and the rest of them stays in this bucket. If fewer than `k` scores remain, all of
+    /// them are moved. The two buckets hold disjoint indices, so no index can be drawn from
+    /// both. The new bucket gets a copy of the original scores of the whole list, so that
+    /// `original_score` and `reset_noise` keep working for the indices it holds; only the
+    /// scores that were moved are redrawn by its `reset_noise`.
+    pub fn split_off(&mut self, k: usize) -> GumbelTopBucket<F> {
+        let k = k.min(self.scores_len);
+        let noisy_scores: Vec<(usize, F)> = self
+            .noisy_scores
+            .drain(self.cursor..self.cursor + k)
+            .collect();
+        self.scores_len -= k;
+        GumbelTopBucket {
+            scores_len: noisy_scores.len(),
+            scores: self.scores.clone(),
+            temperature: self.temperature,
+            noisy_scores,
+            cursor: 0,
+        }
+    }
+
+    /// Draw from the bucket, starting a new epoch when it is empty: the Gumbel noise is then
+    /// re-rolled with `reset_noise`, at the temperature of the bucket, and the draw is made from
+    /// the fresh noisy scores. Within an epoch no index is drawn twice, but across the epoch
+    /// boundary they can be, so the first draw of an epoch may repeat the last draw of the
+    /// previous one. Every score that is still part of the bucket takes part in each epoch;
+    /// the ones dropped with `remove_index` or `retain` do not come back. This panics if
+    /// the bucket has no single temperature, or if no score is left in it at all.
+    #[cfg(feature = "std")]
+    pub fn draw_cycling(&mut self) -> usize {
+        self.draw_cycling_with_rng(&mut rand::thread_rng())
+    }
+
+    /// Draw from the bucket like `draw_cycling`, but re-rolling the noise at epoch boundaries
+    /// with the given random number generator.
+    pub fn draw_cycling_with_rng<R>(&mut self, rng: &mut R) -> usize
+    where
+        R: Rng + ?Sized,
+    {
+        if self.scores_len == 0 {
+            assert!(
+                !self.temperature.is_nan(),
+                "draw_cycling needs a bucket with a single temperature"
+            );
+            self.reset_noise_with_rng(self.temperature, rng);
+        }
+        self.draw()
+            .expect("draw_cycling on a bucket without scores")
+    }
+
+    /// Replace the original scores of the bucket with new ones, re-rolling the Gumbel noise of
+    /// the remaining indices at the given temperature, while the indices that were already
+    /// drawn stay drawn. This supports distributions that change between draw phases without
+    /// losing the no-replacement history. Only the remaining part of the bucket is re-sorted.
+    /// `new_scores` must cover the whole original list, i.e. have a length of
+    /// `original_len()`, and this panics otherwise.
+    #[cfg(feature = "std")]
+    pub fn update_scores<T>(&mut self, new_scores: &[T], temperature: F)
+    where
+        T: FloatAdd<F> + Copy,
+    {
+        self.update_scores_with_rng(new_scores, temperature, &mut rand::thread_rng())
+    }
+
+    /// Replace the original scores of the bucket like `update_scores`, but drawing the Gumbel
+    /// noise from the given random number generator.
+    pub fn update_scores_with_rng<T, R>(&mut self, new_scores: &[T], temperature: F, rng: &mut R)
+    where
+        T: FloatAdd<F> + Copy,
+        R: Rng + ?Sized,
+    {
+        assert_eq!(
+            new_scores.len(),
+            self.scores.len(),
+            "new_scores must have the same length as the original list"
+        );
+        for (score, &new_score) in self.scores.iter_mut().zip(new_scores) {
+            *score = new_score.float_add(F::zero());
+        }
+        let between = uniform();
+        let end = self.end();
+        for (idx, noisy_score) in self.noisy_scores[self.cursor..end].iter_mut() {
+            *noisy_score = self.scores[*idx] + gumbel_as(between.sample(rng), temperature);
+        }
+        Self::sort_noisy_scores(&mut self.noisy_scores[self.cursor..end]);
+        self.temperature = temperature;
+    }
+
+    /// Draw up to `k` indices whose *original* score is greater than `threshold`, in draw order.
+    /// As the bucket is sorted by noisy score, a low original score does not mean that the
+    /// scores after it are low too, so every remaining score is checked until `k` were drawn
+    /// or the bucket was scanned to the end. The scores that are skipped for being at or below
+    /// the threshold are *not* removed: they stay in the bucket, in their original order, and
+    /// can still be drawn later, as with `draw_filtered`.
+    pub fn draw_top_k_above(&mut self, k: usize, threshold: F) -> Vec<usize> {
+        let mut drawn = Vec::new();
+        let mut skipped = Vec::new();
+        let mut pos = self.cursor;
+        while drawn.len() < k && pos < self.end() {
+            let entry = self.noisy_scores[pos];
+            if self.scores[entry.0] > threshold {
+                drawn.push(entry);
+            } else {
+                skipped.push(entry);
+            }
+            pos += 1;
+        }
+        // the drawn entries go before the cursor, followed by the skipped ones.
+        for (slot, &entry) in self.noisy_scores[self.cursor..pos]
+            .iter_mut()
+            .zip(drawn.iter().chain(&skipped))
+        {
+            *slot = entry;
+        }
+        self.cursor += drawn.len();
+        self.scores_len -= drawn.len();
+        drawn.into_iter().map(|(idx, _)| idx).collect()
+    }
+
+    /// Asserts the internal invariants of the bucket, panicking if one of them does not hold:
+    /// the remaining noisy scores are sorted in draw order, no index appears twice (whether
+    /// drawn or not), every index is within the original list, and the `remaining()` entries
+    /// after the cursor fit in the bucket. This is a debugging aid for code that mutates the
+    /// bucket, e.g. through `reinsert`, `adjust_score` or `merge`, and is only available with
+    /// debug assertions. Buckets built with `new_presorted_unchecked` may fail the ordering
+    /// check by design.
+    #[cfg(debug_assertions)]
+    pub fn debug_verify(&self) {
+        assert!(
+            self.cursor + self.scores_len <= self.noisy_scores.len(),
+            "remaining entries run past the end of the noisy scores"
+        );
+        let remaining = self.as_slice();
+        for pair in remaining.windows(2) {
+            assert!(
+                cmp_entries(&pair[0], &pair[1]) == Ordering::Less,
+                "remaining noisy scores are not sorted: {:?} before {:?}",
+                pair[0].0,
+                pair[1].0
+            );
+        }
+        let mut seen = alloc::vec![false; self.scores.len()];
+        for &(idx, _) in &self.noisy_scores {
+            assert!(idx < self.scores.len(), "index {} is out of bounds", idx);
+            assert!(!seen[idx], "index {} appears twice", idx);
+            seen[idx] = true;
+        }
+    }
+
+    /// Apply `f` to every index in the original list that the bucket stores, whether it was
+    /// already drawn or not, so that later calls to `draw` return the mapped indices. This is
+    /// meant for buckets built over a sub-slice of a larger array: with `f` mapping positions
+    /// in the sub-slice back to positions in the full array, no draw needs to be translated by
+    /// hand. The original scores are moved along with their indices, so `original_score(f(i))`
+    /// is the score that index `i` had before; the positions that no index maps to are filled
+    /// with negative infinity, which gives them no probability, and `original_len()` becomes
+    /// the highest mapped index plus one. `f` must be injective, as two indices mapped to the
+    /// same one would make `draw` return it twice.
+    pub fn map_indices<M>(&mut self, f: M)
+    where
+        M: Fn(usize) -> usize,
+    {
+        let mapped: Vec<usize> = (0..self.scores.len()).map(f).collect();
+        let len = mapped.iter().max().map_or(0, |&idx| idx + 1);
+        let mut scores = alloc::vec![F::neg_infinity(); len];
+        for (&score, &idx) in self.scores.iter().zip(&mapped) {
+            scores[idx] = score;
+        }
+        self.scores = scores;
+        for (idx, _) in self.noisy_scores.iter_mut() {
+            *idx = mapped[*idx];
+        }
+        // ties between equal noisy scores are broken by index, which an `f` that is not
+        // increasing may have reordered.
+        if mapped.windows(2).any(|pair| pair[0] >= pair[1]) {
+            let end = self.end();
+            Self::sort_noisy_scores(&mut self.noisy_scores[self.cursor..end]);
+        }
+    }
+
+    /// Returns the current draw position of the bucket, which can be passed to
+    /// `restore_cursor` to backtrack the draws made since. This is an O(1) alternative to
+    /// cloning the bucket, e.g. for searches with rollback. Only the position of `draw` and
+    /// its variants is saved, not the draws made with `draw_min`.
+    pub fn save_cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Restore a draw position returned by `save_cursor`, so that the scores drawn since are
+    /// remaining again, and are drawn anew in the same order. Restoring a later position than
+    /// the current one draws the scores in between again, which makes it possible to redo a
+    /// backtracked draw. The position is only meaningful while the noisy scores are not
+    /// rearranged: `reset_noise`, `reinsert`, `remove_index`, `retain`, `merge` and the other
+    /// methods that move entries around invalidate it. This panics if the position is past
+    /// the remaining scores.
+    pub fn restore_cursor(&mut self, cursor: usize) {
+        let end = self.end();
+        assert!(cursor <= end, "cursor is past the remaining scores");
+        self.cursor = cursor;
+        self.scores_len = end - cursor;
+    }
+}
+
+impl GumbelTopBucket {
+    /// Create a new heap-backed bucket from a slice of scores and a temperature. This is a
+    /// shorthand for `GumbelHeapBucket::new`; see `GumbelHeapBucket` for when it should be
+    /// preferred over the sorted GumbelTopBucket.
+    #[cfg(feature = "std")]
+    pub fn new_heap<T>(scores: &[T], temperature: f64) -> GumbelHeapBucket
+    where
+        T: F64Add + Copy,
+    {
+        GumbelHeapBucket::new(scores, temperature)
+    }
+}
+
+/// Sample `k` distinct indices from the scores in one call, without building a bucket. The
+/// indices are returned in the order a GumbelTopBucket with the same noise would draw them,
+/// so this is equivalent to `GumbelTopBucket::new_with_rng(scores, temperature, rng).draw_n(k)`.
+/// When `k` is smaller than the number of scores, only the top `k` noisy scores are selected
+/// and sorted, in O(n + k log k), instead of sorting all of them. If `k` is larger than the
+/// number of scores, all of the indices are returned. This panics if the scores are empty or
+/// the temperature is invalid.
+pub fn sample_indices<F, T, R>(scores: &[T], temperature: F, k: usize, rng: &mut R) -> Vec<usize>
+where
+    F: Float,
+    T: FloatAdd<F> + Copy,
+    R: Rng + ?Sized,
+{
+    GumbelTopBucket::<F>::assert_valid(scores.len(), temperature);
+    let between = uniform();
+    let mut noisy_scores: Vec<(usize, F)> = scores
+        .iter()
+        .enumerate()
+        .map(|(i, &score)| {
+            (
+                i,
+                score.float_add(gumbel_as(between.sample(rng), temperature)),
+            )
+        })
+        .collect();
+    if k < noisy_scores.len() {
+        noisy_scores.select_nth_unstable_by(k, cmp_entries);
+        noisy_scores.truncate(k);
+    }
+    GumbelTopBucket::sort_noisy_scores(&mut noisy_scores);
+    noisy_scores.into_iter().map(|(idx, _)| idx).collect()
+}
+
+/// Shuffle the indices of the scores into a weighted random permutation of `0..n`, as if the
+/// indices were drawn one by one without replacement from the softmax of the remaining scores.
+/// This is the order in which draining `GumbelTopBucket::new_with_rng(scores, temperature,
+/// rng)` would yield them, but without storing the original scores. This panics if the scores
+/// are empty or the temperature is invalid.
+pub fn weighted_permutation<F, T, R>(scores: &[T], temperature: F, rng: &mut R) -> Vec<usize>
+where
+    F: Float,
+    T: FloatAdd<F> + Copy,
+    R: Rng + ?Sized,
+{
+    sample_indices(scores, temperature, scores.len(), rng)
+}
+
+/// Sample a single index from the scores, as the argmax of the scores plus Gumbel noise. This
+/// is the lightest way to draw once: it is O(n), like `GumbelTopBucket::new_single`, but does
+/// not allocate at all, as neither the noisy nor the original scores are stored. For the same
+/// random number generator, the result is the index the first `draw` of
+/// `GumbelTopBucket::new_with_rng` would return. Returns None if the scores are empty, and
+/// panics if the temperature is invalid.
+pub fn gumbel_argmax<F, T, R>(scores: &[T], temperature: F, rng: &mut R) -> Option<usize>
+where
+    F: Float,
+    T: FloatAdd<F> + Copy,
+    R: Rng + ?Sized,
+{
+    if let Err(err) = GumbelTopBucket::<F>::validate_temperature(temperature) {
+        panic!("{}", err);
+    }
+    let between = uniform();
+    let mut best: Option<(usize, F)> = None;
+    for (i, &score) in scores.iter().enumerate() {
+        let entry = (
+            i,
+            score.float_add(gumbel_as(between.sample(rng), temperature)),
+        );
+        best = match best {
+            Some(best) if cmp_entries(&best, &entry) != Ordering::Greater => Some(best),
+            _ => Some(entry),
+        };
+    }
+    best.map(|(idx, _)| idx)
+}
+
+/// Shows the number of remaining scores, the temperature, and the next few entries that would
+/// be drawn, as `(index, noisy score)` pairs, which is easier to read than the derived
+/// `Debug` for large buckets.
+impl<F: Float + core::fmt::Display> core::fmt::Display for GumbelTopBucket<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        const SHOWN: usize = 3;
+        write!(
+            f,
+            "GumbelTopBucket {{ remaining: {}, temperature: {}, next: [",
+            self.scores_len, self.temperature
+        )?;
+        for (i, (idx, noisy_score)) in self.as_slice().iter().take(SHOWN).enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "({}, {})", idx, noisy_score)?;
+        }
+        if self.scores_len > SHOWN {
+            write!(f, ", ...")?;
+        }
+        write!(f, "] }}")
+    }
+}
+
+/// The result of `GumbelTopBucket::draw_full`, holding everything that is known about a drawn
+/// score.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawResult<F = f64> {
+    /// The index of the score in the original list.
+    pub index: usize,
+    /// The score after the noise was added, which determines the draw order.
+    pub noisy_score: F,
+    /// The original score, before the noise was added.
+    pub original_score: F,
+}
+
+/// A draining iterator over a GumbelTopBucket, created by `GumbelTopBucket::drain`.
+/// Each call to `next` draws from the bucket, so items that were yielded are removed
+/// from the bucket even if the iterator is dropped early.
+#[derive(Debug)]
+pub struct Drain<'a, F = f64> {
+    bucket: &'a mut GumbelTopBucket<F>,
+}
+
+impl<F: Float> Iterator for Drain<'_, F> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.bucket.draw()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.bucket.remaining();
+        (remaining, Some(remaining))
+    }
+}
+
+impl<F: Float> ExactSizeIterator for Drain<'_, F> {}
+
+/// A draining iterator over a GumbelTopBucket that also yields the *noisy* scores,
+/// created by `GumbelTopBucket::drain_with_scores`.
+#[derive(Debug)]
+pub struct DrainWithScores<'a, F = f64> {
+    bucket: &'a mut GumbelTopBucket<F>,
+}
+
+impl<F: Float> Iterator for DrainWithScores<'_, F> {
+    type Item = (usize, F);
+
+    fn next(&mut self) -> Option<(usize, F)> {
+        self.bucket.draw_with_score()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.bucket.remaining();
+        (remaining, Some(remaining))
+    }
+}
+
+impl<F: Float> ExactSizeIterator for DrainWithScores<'_, F> {}
+
+impl<F: Float> IntoIterator for GumbelTopBucket<F> {
+    type Item = usize;
+    type IntoIter = IntoIter<F>;
+
+    /// Consumes the bucket, returning an iterator that draws from it until it is empty.
+    /// See `drain`.
+    fn into_iter(self) -> IntoIter<F> {
+        IntoIter { bucket: self }
+    }
+}
+
+/// An owning iterator over a GumbelTopBucket, created by its `IntoIterator` implementation.
+/// It yields the remaining indices in draw order.
+#[derive(Debug, Clone)]
+pub struct IntoIter<F = f64> {
+    bucket: GumbelTopBucket<F>,
+}
+
+impl<F: Float> Iterator for IntoIter<F> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.bucket.draw()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.bucket.remaining();
+        (remaining, Some(remaining))
+    }
+}
+
+impl<F: Float> ExactSizeIterator for IntoIter<F> {}
+
+/// An owning iterator over a GumbelTopBucket that also yields the *noisy* scores, created
+/// by `GumbelTopBucket::into_iter_with_scores`.
+#[derive(Debug, Clone)]
+pub struct IntoIterWithScores<F = f64> {
+    bucket: GumbelTopBucket<F>,
+}
+
+impl<F: Float> Iterator for IntoIterWithScores<F> {
+    type Item = (usize, F);
+
+    fn next(&mut self) -> Option<(usize, F)> {
+        self.bucket.draw_with_score()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.bucket.remaining();
+        (remaining, Some(remaining))
+    }
+}
+
+impl<F: Float> ExactSizeIterator for IntoIterWithScores<F> {}
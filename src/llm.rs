@@ -0,0 +1,39 @@
+use alloc::vec::Vec;
+use rand::Rng;
+
+use crate::sample_indices;
+
+/// Sample up to `top_k` distinct tokens from the logits of a language model, as a drop-in
+/// replacement for top-k sampling: the tokens are drawn without replacement from the softmax
+/// of `logits / temperature`, in the order they were drawn, so the first token is a regular
+/// temperature sample and the whole vector is a sample of the `top_k` best tokens under the
+/// Gumbel-top-k trick. Masked tokens, whose logit is negative infinity (or NaN), are never
+/// returned, so fewer than `top_k` tokens come back when not enough of them are unmasked.
+/// Only `top_k` noisy logits are sorted, so this is O(n + k log k) for a vocabulary of size
+/// n. This panics if the logits are empty or the temperature is invalid.
+#[cfg(feature = "std")]
+pub fn sample_tokens(logits: &[f32], temperature: f64, top_k: usize) -> Vec<usize> {
+    sample_tokens_with_rng(logits, temperature, top_k, &mut rand::thread_rng())
+}
+
+/// Sample tokens like `sample_tokens`, but drawing the Gumbel noise from the given random
+/// number generator.
+pub fn sample_tokens_with_rng<R>(
+    logits: &[f32],
+    temperature: f64,
+    top_k: usize,
+    rng: &mut R,
+) -> Vec<usize>
+where
+    R: Rng + ?Sized,
+{
+    let mut tokens = sample_indices(logits, temperature, top_k, rng);
+    // masked logits stay masked after adding the noise, so they are sorted after every
+    // unmasked token.
+    let unmasked = tokens
+        .iter()
+        .position(|&token| logits[token] == f32::NEG_INFINITY || logits[token].is_nan())
+        .unwrap_or(tokens.len());
+    tokens.truncate(unmasked);
+    tokens
+}
@@ -18,8 +18,9 @@ fn main() {
     println!("total: {}", total);
 
     let max = 1000000;
+    let mut bucket = GumbelTopBucket::new(&l, 1.0);
     for _ in 0..max {
-        let mut bucket = GumbelTopBucket::new(&l, 1.0);
+        bucket.reset_noise_with_rng(1.0, &mut rng);
         if let Some(drawn) = bucket.draw() {
             *freqs.entry(drawn).or_insert(0) += 1;
         }
@@ -27,14 +28,16 @@ fn main() {
     println!("{:?}", freqs);
     // to sorted vector
     let mut freqs: Vec<(usize, usize)> = freqs.into_iter().collect();
-    freqs.sort_unstable_by(|a, b| b.1.cmp(&a.1));
+    freqs.sort_unstable_by_key(|b| std::cmp::Reverse(b.1));
+    let expected = bucket.first_draw_probabilities().unwrap();
     for (k, v) in freqs.iter() {
         println!(
-            "{} ({} - {}): {}",
+            "{} ({} - {}): {} (expected {})",
             k,
             l[*k],
             l_orig[*k],
-            *v as f64 / max as f64
+            *v as f64 / max as f64,
+            expected[*k]
         );
     }
 
@@ -42,7 +45,7 @@ fn main() {
     let l: [f64; 5] = [0.7, 0.2, 0.3, 0.2, 0.2];
     println!("sampling until empty: {:?}", l);
     let mut bucket = GumbelTopBucket::new(&l, 1.0);
-    while let Some(drawn) = bucket.draw() {
+    for drawn in bucket.drain() {
         println!("drawn: {} ({})", drawn, l[drawn]);
     }
 }
@@ -0,0 +1,45 @@
+use rand::distributions::Distribution;
+use rand::Rng;
+
+use crate::{gumbel, uniform};
+
+/// A source of noise that is added to the scores before they are sorted. The GumbelTopBucket
+/// uses `Gumbel` noise by default, which makes the draws follow the softmax of the scores, but
+/// any other perturbation can be plugged in through `GumbelTopBucket::new_with_noise`, turning
+/// the bucket into a general perturb-and-sort sampler. For example, a source that always
+/// returns 0.0 yields a deterministic descending sort of the scores.
+pub trait NoiseSource {
+    /// Sample a single noise value, using the given random number generator.
+    fn sample<R>(&self, rng: &mut R) -> f64
+    where
+        R: Rng + ?Sized;
+}
+
+/// Gumbel(0, temperature) noise, the noise used by `GumbelTopBucket::new`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gumbel {
+    pub temperature: f64,
+}
+
+impl Gumbel {
+    /// Create a new Gumbel noise source with the given temperature (scale).
+    pub fn new(temperature: f64) -> Gumbel {
+        Gumbel { temperature }
+    }
+}
+
+impl Default for Gumbel {
+    fn default() -> Gumbel {
+        Gumbel::new(1.0)
+    }
+}
+
+impl NoiseSource for Gumbel {
+    #[inline]
+    fn sample<R>(&self, rng: &mut R) -> f64
+    where
+        R: Rng + ?Sized,
+    {
+        gumbel(uniform().sample(rng), self.temperature)
+    }
+}
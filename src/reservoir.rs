@@ -0,0 +1,89 @@
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use num_traits::Float;
+use rand::distributions::Distribution;
+use rand::Rng;
+
+use crate::heap::OrderedF64;
+use crate::{gumbel, uniform, F64Add};
+
+/// A GumbelReservoir samples `k` indices without replacement from a stream of scores that is
+/// too large to be held in memory, using weighted reservoir sampling. Each pushed score gets
+/// the key `ln(score) + g`, where `g` is Gumbel(0, 1) noise, and only the `k` highest keys
+/// are kept, in a min-heap. This is the Gumbel-top-k trick the GumbelTopBucket is built on,
+/// applied online (it is equivalent to the A-ES algorithm): the final top `k` are distributed
+/// like the first `k` draws of `GumbelTopBucket::new_from_logits` on the logarithms of the
+/// scores, with a temperature of 1. The scores are therefore weights, and should be
+/// non-negative; a score of 0 is only kept while fewer than `k` scores were pushed.
+#[derive(Debug, Clone)]
+pub struct GumbelReservoir {
+    k: usize,
+    // the number of scores pushed so far, which is the index of the next one.
+    pushed: usize,
+    // a min-heap, so that the lowest of the kept keys can be evicted; the index is reversed
+    // so that, like in the GumbelTopBucket, ties are won by the earlier index.
+    keys: BinaryHeap<Reverse<(OrderedF64, Reverse<usize>)>>,
+}
+
+impl GumbelReservoir {
+    /// Create a new, empty GumbelReservoir that keeps the `k` highest keys.
+    pub fn new(k: usize) -> GumbelReservoir {
+        GumbelReservoir {
+            k,
+            pushed: 0,
+            keys: BinaryHeap::with_capacity(k),
+        }
+    }
+
+    /// Push the next score of the stream into the reservoir. Its index is the number of
+    /// scores pushed before it.
+    #[cfg(feature = "std")]
+    pub fn push<T>(&mut self, score: T)
+    where
+        T: F64Add,
+    {
+        self.push_with_rng(score, &mut rand::thread_rng())
+    }
+
+    /// Push the next score of the stream into the reservoir like `push`, but drawing the
+    /// Gumbel noise from the given random number generator.
+    pub fn push_with_rng<T, R>(&mut self, score: T, rng: &mut R)
+    where
+        T: F64Add,
+        R: Rng + ?Sized,
+    {
+        let idx = self.pushed;
+        self.pushed += 1;
+        let key = Float::ln(score.float_add(0.0)) + gumbel(uniform().sample(rng), 1.0);
+        let entry = Reverse((OrderedF64(key), Reverse(idx)));
+        if self.keys.len() < self.k {
+            self.keys.push(entry);
+        } else if let Some(mut lowest) = self.keys.peek_mut() {
+            if entry < *lowest {
+                *lowest = entry;
+            }
+        }
+    }
+
+    /// Returns the number of indices kept in the reservoir, which is at most `k`.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns true if no index is kept in the reservoir.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Consumes the reservoir, returning the kept indices of the stream together with their
+    /// keys, ordered by descending key, which is the order in which a GumbelTopBucket would
+    /// draw them.
+    pub fn into_sorted(self) -> Vec<(usize, f64)> {
+        self.keys
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((key, Reverse(idx)))| (idx, key.0))
+            .collect()
+    }
+}
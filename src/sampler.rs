@@ -0,0 +1,56 @@
+use num_traits::Float;
+
+use crate::{GumbelHeapBucket, GumbelTopBucket, GumbelTopBucketN};
+
+/// A sampler that draws indices without replacement, each index being drawn at most once.
+/// It is implemented by `GumbelTopBucket`, `GumbelTopBucketN` and `GumbelHeapBucket`, so
+/// that generic code can accept any of them, as well as other samplers or mocks that
+/// implement it.
+pub trait WeightedWithoutReplacement {
+    /// Draw the next index, removing it from the sampler, or return None if it is empty.
+    fn draw(&mut self) -> Option<usize>;
+
+    /// Returns the number of indices that can still be drawn.
+    fn remaining(&self) -> usize;
+
+    /// Returns true if there are no indices left to draw.
+    fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+}
+
+impl<F: Float> WeightedWithoutReplacement for GumbelTopBucket<F> {
+    #[inline]
+    fn draw(&mut self) -> Option<usize> {
+        GumbelTopBucket::draw(self)
+    }
+
+    #[inline]
+    fn remaining(&self) -> usize {
+        GumbelTopBucket::remaining(self)
+    }
+}
+
+impl WeightedWithoutReplacement for GumbelHeapBucket {
+    #[inline]
+    fn draw(&mut self) -> Option<usize> {
+        GumbelHeapBucket::draw(self)
+    }
+
+    #[inline]
+    fn remaining(&self) -> usize {
+        GumbelHeapBucket::remaining(self)
+    }
+}
+
+impl<const N: usize, F: Float> WeightedWithoutReplacement for GumbelTopBucketN<N, F> {
+    #[inline]
+    fn draw(&mut self) -> Option<usize> {
+        GumbelTopBucketN::draw(self)
+    }
+
+    #[inline]
+    fn remaining(&self) -> usize {
+        GumbelTopBucketN::remaining(self)
+    }
+}
@@ -0,0 +1,67 @@
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use num_traits::Float;
+
+use crate::GumbelTopBucket;
+
+/// A SharedGumbelBucket hands out the remaining draws of a GumbelTopBucket to several threads
+/// at once. As the noisy scores are already sorted, each draw only has to claim the next slot,
+/// which is done by advancing the cursor with an atomic compare-and-swap loop that stops at
+/// the end of the bucket. Draws therefore take `&self` and are lock-free, while still never
+/// yielding the same index twice across threads. The
+/// order in which the threads receive the indices depends on scheduling, but together they
+/// receive exactly the draws a single thread would have.
+#[derive(Debug)]
+pub struct SharedGumbelBucket<F = f64> {
+    noisy_scores: Vec<(usize, F)>,
+    // position of the next entry to draw in `noisy_scores`; never past its length, which it
+    // equals once the bucket is empty.
+    cursor: AtomicUsize,
+}
+
+impl<F: Float> SharedGumbelBucket<F> {
+    /// Create a new SharedGumbelBucket from the scores that have not been drawn yet from the
+    /// given bucket. This is O(1), as the noisy scores are moved over as they are.
+    pub fn new(bucket: GumbelTopBucket<F>) -> SharedGumbelBucket<F> {
+        let mut noisy_scores = bucket.noisy_scores;
+        // drop the entries drawn with `draw_min`, which sit after the remaining ones.
+        noisy_scores.truncate(bucket.cursor + bucket.scores_len);
+        SharedGumbelBucket {
+            noisy_scores,
+            cursor: AtomicUsize::new(bucket.cursor),
+        }
+    }
+
+    /// Draw a score from the bucket. This returns the index of the score in the original list,
+    /// as well as the *noisy* score, or None if the bucket is empty. It can be called from
+    /// several threads at once, and each call gets a distinct index.
+    #[must_use]
+    pub fn draw(&self) -> Option<(usize, F)> {
+        let len = self.noisy_scores.len();
+        let pos = self
+            .cursor
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |pos| {
+                (pos < len).then(|| pos + 1)
+            })
+            .ok()?;
+        Some(self.noisy_scores[pos])
+    }
+
+    /// Returns the number of scores that can still be drawn from the bucket. When other
+    /// threads are drawing concurrently, this is only a snapshot.
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.noisy_scores.len() - self.cursor.load(Ordering::Relaxed)
+    }
+
+    /// Returns true if there are no scores left to draw from the bucket.
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+}
+
+impl<F: Float> From<GumbelTopBucket<F>> for SharedGumbelBucket<F> {
+    fn from(bucket: GumbelTopBucket<F>) -> Self {
+        SharedGumbelBucket::new(bucket)
+    }
+}
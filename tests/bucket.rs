@@ -0,0 +1,978 @@
+#![cfg(feature = "std")]
+
+use gumbel_top_bucket::{
+    GumbelBucketBuilder, GumbelError, GumbelReservoir, GumbelTopBucket, GumbelTopBucketKeyed,
+    GumbelTopBucketN, SharedGumbelBucket, WeightedWithoutReplacement,
+};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+#[test]
+fn nan_score_is_drawn_last() {
+    for _ in 0..100 {
+        let mut bucket = GumbelTopBucket::new(&[0.5, f64::NAN, 0.3], 1.0);
+        let drawn: Vec<usize> = bucket.drain().collect();
+        assert_eq!(drawn.len(), 3);
+        assert_eq!(drawn[2], 1);
+    }
+}
+
+#[test]
+fn try_new_rejects_nan_score() {
+    let err = GumbelTopBucket::try_new(&[0.5, f64::NAN, 0.3], 1.0).unwrap_err();
+    assert_eq!(err, GumbelError::NonFiniteScore(1));
+}
+
+#[test]
+fn reinsert_then_reset_noise_draws_each_index_once() {
+    let mut bucket = GumbelTopBucket::new(&[0.1, 0.4, 0.2, 0.9], 1.0);
+    let first = bucket.draw().unwrap();
+    assert!(bucket.reinsert(first, 0.3, 1.0));
+    bucket.reset_noise(1.0);
+    let mut drawn: Vec<usize> = bucket.drain().collect();
+    drawn.sort_unstable();
+    assert_eq!(drawn, vec![0, 1, 2, 3]);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip_preserves_remaining_draws() {
+    let mut bucket = GumbelTopBucket::new(&[0.1, 0.4, 0.2, 0.9, 0.5, 0.3], 1.0);
+    bucket.draw_n(2);
+    let json = serde_json::to_string(&bucket).unwrap();
+    let mut restored: GumbelTopBucket = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.remaining(), bucket.remaining());
+    let expected: Vec<(usize, f64)> = bucket.drain_with_scores().collect();
+    let actual: Vec<(usize, f64)> = restored.drain_with_scores().collect();
+    assert_eq!(actual, expected);
+}
+
+#[cfg(feature = "half")]
+#[test]
+fn half_scores() {
+    use half::{bf16, f16};
+
+    let scores: Vec<f16> = [0.1, 0.7, 0.2].iter().map(|&x| f16::from_f64(x)).collect();
+    let mut bucket = GumbelTopBucket::new(&scores, 1.0);
+    let mut drawn: Vec<usize> = bucket.drain().collect();
+    drawn.sort_unstable();
+    assert_eq!(drawn, vec![0, 1, 2]);
+
+    let scores: Vec<bf16> = [0.1, 0.7, 0.2].iter().map(|&x| bf16::from_f64(x)).collect();
+    let bucket = GumbelTopBucket::new(&scores, 1.0);
+    assert_eq!(bucket.remaining(), 3);
+    assert_eq!(bucket.original_score(1), Some(bf16::from_f64(0.7).to_f64()));
+}
+
+#[test]
+fn logits_with_large_gap_draw_highest_first() {
+    let mut rng = StdRng::seed_from_u64(21);
+    let logits = [0.0, 20.0, 1.0, -5.0];
+    for _ in 0..1000 {
+        let mut bucket = GumbelTopBucket::new_from_logits_with_rng(&logits, 1.0, &mut rng);
+        assert_eq!(bucket.draw(), Some(1));
+    }
+}
+
+#[test]
+fn empty_scores_are_rejected() {
+    let scores: [f64; 0] = [];
+    assert_eq!(
+        GumbelTopBucket::try_new(&scores, 1.0).unwrap_err(),
+        GumbelError::EmptyScores
+    );
+    assert!(std::panic::catch_unwind(|| GumbelTopBucket::new(&scores, 1.0)).is_err());
+}
+
+#[test]
+fn gumbel_noise_of_size_zero_is_empty() {
+    assert!(GumbelTopBucket::gumbel_noise(0, 1.0).is_empty());
+}
+
+#[test]
+fn single_score_is_drawn_once() {
+    let mut bucket = GumbelTopBucket::new(&[0.5], 1.0);
+    assert_eq!(bucket.remaining(), 1);
+    assert_eq!(bucket.peek(), Some(0));
+    assert_eq!(bucket.draw(), Some(0));
+    assert!(bucket.is_empty());
+    assert_eq!(bucket.draw(), None);
+    assert_eq!(bucket.peek(), None);
+}
+
+#[test]
+fn draw_after_clear_is_none() {
+    let mut bucket = GumbelTopBucket::new(&[0.1, 0.5, 0.9], 1.0);
+    assert!(bucket.draw().is_some());
+    bucket.clear();
+    assert!(bucket.is_empty());
+    assert_eq!(bucket.draw(), None);
+    assert_eq!(bucket.peek(), None);
+    bucket.reset_noise(1.0);
+    assert_eq!(bucket.remaining(), 3);
+}
+
+#[test]
+fn shared_bucket_draws_each_index_once_across_threads() {
+    let scores: Vec<f64> = (0..1000).map(|i| i as f64 / 100.0).collect();
+    let bucket = SharedGumbelBucket::new(GumbelTopBucket::new(&scores, 1.0));
+    let mut drawn: Vec<usize> = std::thread::scope(|s| {
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut drawn = Vec::new();
+                    while let Some((idx, _)) = bucket.draw() {
+                        drawn.push(idx);
+                    }
+                    drawn
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap())
+            .collect()
+    });
+    drawn.sort_unstable();
+    assert_eq!(drawn, (0..1000).collect::<Vec<_>>());
+    assert!(bucket.is_empty());
+}
+
+#[test]
+fn shared_bucket_stays_empty_after_draining() {
+    let bucket = SharedGumbelBucket::new(GumbelTopBucket::new(&[0.5, 0.7], 1.0));
+    assert!(bucket.draw().is_some());
+    assert!(bucket.draw().is_some());
+    for _ in 0..10 {
+        assert_eq!(bucket.draw(), None);
+    }
+    assert_eq!(bucket.remaining(), 0);
+    assert!(bucket.is_empty());
+}
+
+#[test]
+fn infinite_scores_are_drawn_first_and_last() {
+    for _ in 0..100 {
+        let mut bucket = GumbelTopBucket::new(&[f64::INFINITY, 0.5, f64::NEG_INFINITY], 1.0);
+        assert_eq!(bucket.draw_n(3), vec![0, 1, 2]);
+    }
+    let scores = [
+        0.5,
+        f64::INFINITY,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        f64::INFINITY,
+    ];
+    let mut bucket = GumbelTopBucket::new(&scores, 1.0);
+    assert_eq!(bucket.draw_n(5), vec![1, 2, 4, 0, 3]);
+    let mut bucket = GumbelTopBucket::new_heap(&scores, 1.0);
+    assert_eq!(bucket.draw(), Some(1));
+    assert_eq!(bucket.draw(), Some(2));
+    assert_eq!(bucket.draw(), Some(4));
+}
+
+#[test]
+fn infinite_scores_get_all_the_first_draw_probability() {
+    let bucket: GumbelTopBucket =
+        GumbelTopBucket::new(&[0.5, f64::INFINITY, f64::NEG_INFINITY, f64::INFINITY], 1.0);
+    assert_eq!(
+        bucket.first_draw_probabilities(),
+        Some(vec![0.0, 1.0, 0.0, 0.0])
+    );
+    assert_eq!(bucket.probability(1), Some(1.0));
+    assert_eq!(bucket.probability(0), Some(0.0));
+    assert_eq!(bucket.probability(3), Some(0.0));
+}
+
+#[test]
+#[ignore = "statistical test, slow in debug builds"]
+fn first_draw_frequencies_match_softmax() {
+    const TRIALS: usize = 200_000;
+    let scores = [1.0, 2.0, 0.5, 3.0, -1.0];
+    let mut rng = StdRng::seed_from_u64(49);
+    let mut counts = [0usize; 5];
+    for _ in 0..TRIALS {
+        let mut bucket = GumbelTopBucket::new_with_rng(&scores, 0.8, &mut rng);
+        counts[bucket.draw().unwrap()] += 1;
+    }
+    let bucket = GumbelTopBucket::new_with_rng(&scores, 0.8, &mut rng);
+    for (idx, &count) in counts.iter().enumerate() {
+        let expected = bucket.probability(idx).unwrap();
+        let empirical = count as f64 / TRIALS as f64;
+        assert!(
+            (empirical - expected).abs() < 0.005,
+            "index {}: empirical {} vs expected {}",
+            idx,
+            empirical,
+            expected
+        );
+    }
+}
+
+#[test]
+fn tiny_temperature_draws_in_score_order() {
+    let scores = [0.3, 0.9, 0.1, 0.5, 0.5, 0.7];
+    for _ in 0..100 {
+        let mut bucket = GumbelTopBucket::new(&scores, f64::MIN_POSITIVE);
+        assert_eq!(bucket.draw_n(6), vec![1, 5, 3, 4, 0, 2]);
+    }
+}
+
+#[test]
+fn huge_temperature_draws_close_to_uniform() {
+    const TRIALS: usize = 30_000;
+    let scores = [0.0, 1.0, 10.0];
+    let mut rng = StdRng::seed_from_u64(54);
+    let mut counts = [0usize; 3];
+    for _ in 0..TRIALS {
+        let mut bucket = GumbelTopBucket::new_with_rng(&scores, 1e6, &mut rng);
+        counts[bucket.draw().unwrap()] += 1;
+    }
+    for count in counts {
+        assert!((count as f64 / TRIALS as f64 - 1.0 / 3.0).abs() < 0.02);
+    }
+}
+
+#[test]
+fn equal_noisy_scores_are_drawn_in_index_order() {
+    let scores = [1u8, 2, 1, 2, 1];
+    let noise = [0.0; 5];
+    let mut bucket = GumbelTopBucket::new_with_noise_slice(&scores, &noise);
+    assert_eq!(bucket.draw_n(5), vec![1, 3, 0, 2, 4]);
+}
+
+#[test]
+fn draw_budget_composition_matches_successive_softmax_draws() {
+    const TRIALS: usize = 50_000;
+    let scores = [0.0, 1.0, 2.0];
+    let weights: Vec<f64> = scores.iter().map(|&s: &f64| s.exp()).collect();
+    let total: f64 = weights.iter().sum();
+    let mut rng = StdRng::seed_from_u64(64);
+    let mut counts = [0usize; 3];
+    for _ in 0..TRIALS {
+        let mut bucket = GumbelTopBucket::new_with_rng(&scores, 1.0, &mut rng);
+        for idx in bucket.draw_budget(2) {
+            counts[idx] += 1;
+        }
+    }
+    for (i, &count) in counts.iter().enumerate() {
+        // drawn first, or drawn second after some other index j.
+        let expected = weights[i] / total
+            + (0..3)
+                .filter(|&j| j != i)
+                .map(|j| weights[j] / total * weights[i] / (total - weights[j]))
+                .sum::<f64>();
+        let empirical = count as f64 / TRIALS as f64;
+        assert!(
+            (empirical - expected).abs() < 0.01,
+            "index {}: empirical {} vs expected {}",
+            i,
+            empirical,
+            expected
+        );
+    }
+}
+
+#[test]
+fn display_shows_the_next_entries() {
+    let mut bucket = GumbelTopBucket::new_with_noise_slice(&[0.5, 1.5, 2.5, 3.5, 4.5], &[0.0; 5]);
+    assert_eq!(
+        bucket.to_string(),
+        "GumbelTopBucket { remaining: 5, temperature: NaN, next: [(4, 4.5), (3, 3.5), (2, 2.5), ...] }"
+    );
+    assert_eq!(bucket.draw_n(3), vec![4, 3, 2]);
+    assert_eq!(
+        bucket.to_string(),
+        "GumbelTopBucket { remaining: 2, temperature: NaN, next: [(1, 1.5), (0, 0.5)] }"
+    );
+}
+
+#[cfg(feature = "ndarray")]
+#[test]
+fn ndarray_column_view() {
+    let logits = ndarray::arr2(&[[0.1f32, 9.0], [0.2, f32::NEG_INFINITY], [0.3, 5.0]]);
+    let mut bucket = GumbelTopBucket::new_from_ndarray(logits.column(1), 0.01);
+    assert_eq!(bucket.draw_n(3), vec![0, 2, 1]);
+    assert_eq!(bucket.original_score(2), Some(5.0));
+}
+
+#[test]
+fn keyed_bucket_draws_every_key_once() {
+    let weights: std::collections::HashMap<&str, f64> =
+        [("a", 0.1), ("b", 0.5), ("c", 0.4)].into_iter().collect();
+    let mut bucket = GumbelTopBucketKeyed::new(weights, 1.0);
+    let mut drawn = Vec::new();
+    while let Some(key) = bucket.draw() {
+        drawn.push(key);
+    }
+    drawn.sort_unstable();
+    assert_eq!(drawn, vec!["a", "b", "c"]);
+}
+
+#[cfg(debug_assertions)]
+#[test]
+fn mutations_keep_the_invariants() {
+    let mut rng = StdRng::seed_from_u64(75);
+    let scores = [0.1, 0.4, 0.2, 0.9, 0.5];
+    let mut bucket = GumbelTopBucket::new_with_rng(&scores, 1.0, &mut rng);
+    let first = bucket.draw().unwrap();
+    bucket.debug_verify();
+    assert!(bucket.reinsert_with_rng(first, 0.3, 1.0, &mut rng));
+    bucket.debug_verify();
+    assert!(bucket.adjust_score(2, 5.0));
+    bucket.debug_verify();
+    assert!(bucket.draw_min().is_some());
+    bucket.debug_verify();
+    bucket.merge(GumbelTopBucket::new_with_rng(&[0.7, 0.8], 1.0, &mut rng));
+    bucket.debug_verify();
+    bucket.reset_noise_with_rng(1.0, &mut rng);
+    bucket.debug_verify();
+    let mut drawn = bucket.drain().collect::<Vec<_>>();
+    drawn.sort_unstable();
+    assert_eq!(drawn, (0..7).collect::<Vec<_>>());
+}
+
+#[test]
+fn draw_and_draw_min_meet_in_the_middle() {
+    let mut bucket = GumbelTopBucket::new_with_noise_slice(&[0.5, 1.5, 2.5, 3.5, 4.5], &[0.0; 5]);
+    assert_eq!(bucket.draw(), Some(4));
+    assert_eq!(bucket.draw_min(), Some(0));
+    assert_eq!(bucket.draw_min_with_score(), Some((1, 1.5)));
+    assert_eq!(bucket.sorted_indices(), vec![3, 2]);
+    assert_eq!(bucket.rank_of(2), Some(1));
+    assert_eq!(bucket.rank_of(1), None);
+    assert_eq!(bucket.draw(), Some(3));
+    assert_eq!(bucket.draw_min(), Some(2));
+    assert_eq!(bucket.draw(), None);
+    assert_eq!(bucket.draw_min(), None);
+
+    let mut bucket = GumbelTopBucket::new(&[0.1, 0.4, 0.2, 0.9], 1.0);
+    assert!(bucket.draw_min().is_some());
+    bucket.reset_noise(1.0);
+    let mut drawn: Vec<usize> = bucket.drain().collect();
+    drawn.sort_unstable();
+    assert_eq!(drawn, vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn mapped_indices_point_into_the_full_array() {
+    let full = [0.9, -1.0, 0.4, -1.0, 0.7];
+    let kept: Vec<usize> = (0..full.len()).filter(|&i| full[i] >= 0.0).collect();
+    let sub: Vec<f64> = kept.iter().map(|&i| full[i]).collect();
+    let mut bucket = GumbelTopBucket::new(&sub, 1.0);
+    bucket.map_indices(|i| kept[i]);
+    assert_eq!(bucket.original_len(), 5);
+    assert_eq!(bucket.original_score(4), Some(0.7));
+    let mut drawn: Vec<usize> = bucket.drain().collect();
+    drawn.sort_unstable();
+    assert_eq!(drawn, kept);
+}
+
+#[test]
+fn restore_cursor_undraws_scores() {
+    let mut bucket = GumbelTopBucket::new(&[0.1, 0.4, 0.2, 0.9, 0.5], 1.0);
+    assert!(bucket.draw().is_some());
+    let saved = bucket.save_cursor();
+    let expected = bucket.sorted_indices();
+    let drawn = bucket.draw_n(3);
+    assert_eq!(bucket.remaining(), 1);
+    bucket.restore_cursor(saved);
+    assert_eq!(bucket.remaining(), 4);
+    assert_eq!(bucket.sorted_indices(), expected);
+    assert_eq!(bucket.draw_n(3), drawn);
+}
+
+#[test]
+fn location_shifts_the_noise() {
+    let mut rng = StdRng::seed_from_u64(82);
+    let noise: Vec<f64> = GumbelTopBucket::gumbel_noise_with_rng(100, 1.0, &mut rng);
+    let mut rng = StdRng::seed_from_u64(82);
+    let shifted = GumbelTopBucket::gumbel_noise_loc_with_rng(100, 3.0, 1.0, &mut rng);
+    for (a, b) in noise.iter().zip(&shifted) {
+        assert!((b - a - 3.0).abs() < 1e-12);
+    }
+
+    let scores = [0.1f64, 0.4, 0.2, 0.9];
+    let mut bucket = GumbelTopBucket::new_with_rng(&scores, 1.0, &mut StdRng::seed_from_u64(7));
+    let mut shifted = GumbelTopBucket::new_with_location_with_rng(
+        &scores,
+        3.0,
+        1.0,
+        &mut StdRng::seed_from_u64(7),
+    );
+    for _ in 0..scores.len() {
+        let (idx, noisy_score) = bucket.draw_with_score().unwrap();
+        let (shifted_idx, shifted_score) = shifted.draw_with_score().unwrap();
+        assert_eq!(idx, shifted_idx);
+        assert!((shifted_score - noisy_score - 3.0).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn bucket_iterates_by_value_in_draw_order() {
+    let bucket = GumbelTopBucket::new_with_noise_slice(&[0.5, 1.5, 2.5], &[0.0; 3]);
+    let iter = bucket.clone().into_iter();
+    assert_eq!(iter.len(), 3);
+    assert_eq!(iter.collect::<Vec<_>>(), vec![2, 1, 0]);
+    let mut drawn = Vec::new();
+    for (idx, noisy_score) in bucket.into_iter_with_scores() {
+        drawn.push((idx, noisy_score));
+    }
+    assert_eq!(drawn, vec![(2, 2.5), (1, 1.5), (0, 0.5)]);
+}
+
+#[test]
+fn samplers_are_interchangeable() {
+    fn drain_all<S: WeightedWithoutReplacement>(mut sampler: S) -> Vec<usize> {
+        let mut drawn = Vec::with_capacity(sampler.remaining());
+        while let Some(idx) = sampler.draw() {
+            drawn.push(idx);
+        }
+        assert!(sampler.is_empty());
+        drawn.sort_unstable();
+        drawn
+    }
+    let scores = [0.1, 0.4, 0.2, 0.9];
+    assert_eq!(
+        drain_all(GumbelTopBucket::new(&scores, 1.0)),
+        vec![0, 1, 2, 3]
+    );
+    assert_eq!(
+        drain_all(GumbelTopBucket::new_heap(&scores, 1.0)),
+        vec![0, 1, 2, 3]
+    );
+}
+
+#[test]
+fn rank_fraction_of_drawn_scores() {
+    let mut bucket = GumbelTopBucket::new_with_noise_slice(&[0.5, 3.0, 1.0], &[2.0, 0.0, 0.0]);
+    // noisy scores are 2.5, 3.0 and 1.0.
+    assert_eq!(bucket.draw_with_rank_fraction(), Some((1, 1.0)));
+    assert_eq!(bucket.draw_with_rank_fraction(), Some((0, 0.0)));
+    assert_eq!(bucket.draw_with_rank_fraction(), Some((2, 1.0)));
+    assert_eq!(bucket.draw_with_rank_fraction(), None);
+}
+
+#[test]
+fn try_new_rejects_inexact_integer_scores() {
+    let exact: [u64; 2] = [1 << 53, 3];
+    assert!(GumbelTopBucket::try_new(&exact, 1.0).is_ok());
+    let inexact: [i64; 3] = [1, -(1 << 53) - 1, 3];
+    assert_eq!(
+        GumbelTopBucket::try_new(&inexact, 1.0).unwrap_err(),
+        GumbelError::InexactScore(1)
+    );
+}
+
+#[cfg(feature = "llm")]
+#[test]
+fn sample_tokens_skips_masked_logits() {
+    use gumbel_top_bucket::sample_tokens_with_rng;
+
+    let mut rng = StdRng::seed_from_u64(87);
+    let mut logits = vec![f32::NEG_INFINITY; 50257];
+    for token in [3, 1000, 50256] {
+        logits[token] = 0.0;
+    }
+    for _ in 0..100 {
+        let mut tokens = sample_tokens_with_rng(&logits, 1.0, 5, &mut rng);
+        tokens.sort_unstable();
+        assert_eq!(tokens, vec![3, 1000, 50256]);
+    }
+    assert_eq!(sample_tokens_with_rng(&logits, 1.0, 2, &mut rng).len(), 2);
+    assert!(sample_tokens_with_rng(&logits, 1.0, 0, &mut rng).is_empty());
+}
+
+#[test]
+fn split_off_moves_the_next_draws() {
+    let mut bucket = GumbelTopBucket::new(&[0.1, 0.4, 0.2, 0.9, 0.5], 1.0);
+    assert!(bucket.draw().is_some());
+    let order = bucket.sorted_indices();
+    let mut front = bucket.split_off(2);
+    assert_eq!(front.sorted_indices(), order[..2]);
+    assert_eq!(bucket.sorted_indices(), order[2..]);
+    front.reset_noise(1.0);
+    let mut drawn: Vec<usize> = front.drain().collect();
+    drawn.sort_unstable();
+    let mut expected = order[..2].to_vec();
+    expected.sort_unstable();
+    assert_eq!(drawn, expected);
+    assert_eq!(bucket.split_off(10).remaining(), 2);
+    assert!(bucket.is_empty());
+}
+
+#[test]
+fn gumbel_from_known_uniforms() {
+    let uniforms = [(-1.0f64).exp(), (-(-1.0f64).exp()).exp()];
+    let noise: Vec<f64> = GumbelTopBucket::gumbel_from_uniforms(&uniforms, 2.0);
+    assert!(noise[0].abs() < 1e-12);
+    assert!((noise[1] - 2.0).abs() < 1e-12);
+    assert!(
+        std::panic::catch_unwind(|| GumbelTopBucket::gumbel_from_uniforms(&[0.0], 1.0)).is_err()
+    );
+}
+
+#[test]
+fn gumbel_noise_stays_finite_for_any_eps() {
+    let mut rng = StdRng::seed_from_u64(90);
+    for eps in [f64::MIN_POSITIVE, 1e-300, 1e-10, 0.1, 0.49] {
+        let noise: Vec<f64> =
+            GumbelTopBucket::gumbel_noise_eps_with_rng(10_000, 1.0, eps, &mut rng);
+        assert!(noise.iter().all(|x| x.is_finite()), "eps {}", eps);
+    }
+    assert!(
+        std::panic::catch_unwind(|| GumbelTopBucket::<f64>::gumbel_noise_eps(1, 1.0, 0.0)).is_err()
+    );
+}
+
+#[test]
+fn zero_temperature_sorts_the_raw_scores() {
+    let scores = [0.3, 0.9, 0.1, 0.5, 0.9, 0.5];
+    for _ in 0..10 {
+        let mut bucket = GumbelTopBucket::new(&scores, 0.0);
+        assert_eq!(bucket.draw_n(6), vec![1, 4, 3, 5, 0, 2]);
+        let mut bucket = GumbelTopBucket::new_from_logits(&scores, 0.0);
+        assert_eq!(bucket.draw_n(6), vec![1, 4, 3, 5, 0, 2]);
+    }
+    let bucket = GumbelTopBucket::new(&scores, 0.0);
+    assert_eq!(bucket.probability(1), Some(1.0));
+    assert_eq!(bucket.probability(4), Some(0.0));
+    assert_eq!(
+        GumbelTopBucket::try_new(&scores, -1.0).unwrap_err(),
+        GumbelError::NonPositiveTemperature
+    );
+}
+
+#[test]
+fn draw_n_into_appends_to_the_buffer() {
+    let mut bucket = GumbelTopBucket::new_with_noise_slice(&[0.5, 1.5, 2.5], &[0.0; 3]);
+    let mut buf = vec![7];
+    assert_eq!(bucket.draw_n_into(&mut buf, 2), 2);
+    assert_eq!(bucket.draw_n_into(&mut buf, 2), 1);
+    assert_eq!(bucket.draw_n_into(&mut buf, 2), 0);
+    assert_eq!(buf, vec![7, 2, 1, 0]);
+}
+
+#[test]
+fn bottom_k_keeps_the_lowest_noisy_scores() {
+    let scores: Vec<f64> = (0..100).map(|i| i as f64).collect();
+    let mut rng = StdRng::seed_from_u64(93);
+    let full = GumbelTopBucket::new_with_rng(&scores, 1.0, &mut rng.clone());
+    let mut bucket = GumbelTopBucket::new_bottom_k_with_rng(&scores, 1.0, 5, &mut rng);
+    let lowest = full.sorted_indices()[95..].to_vec();
+    assert_eq!(bucket.sorted_indices(), lowest);
+    assert_eq!(bucket.draw_min(), Some(lowest[4]));
+    assert_eq!(bucket.draw_n(10).len(), 4);
+    assert_eq!(bucket.draw(), None);
+    assert_eq!(
+        GumbelTopBucket::new_bottom_k(&scores, 1.0, 0).remaining(),
+        0
+    );
+}
+
+#[test]
+fn weights_of_all_and_remaining_scores() {
+    let mut bucket = GumbelTopBucket::new_with_noise_slice(&[0.5, 1.5, 2.5], &[0.0; 3]);
+    assert_eq!(bucket.total_weight(), 4.5);
+    assert_eq!(bucket.remaining_weight(), 4.5);
+    assert_eq!(bucket.draw(), Some(2));
+    assert_eq!(bucket.total_weight(), 4.5);
+    assert_eq!(bucket.remaining_weight(), 2.0);
+}
+
+#[test]
+fn fixed_size_bucket_draws_like_the_vec_bucket() {
+    let scores = [0.1, 0.4, 0.2, 0.9, 0.5];
+    let bucket = GumbelTopBucket::new_with_rng(&scores, 1.0, &mut StdRng::seed_from_u64(96));
+    let mut fixed = GumbelTopBucketN::new_with_rng(&scores, 1.0, &mut StdRng::seed_from_u64(96));
+    assert_eq!(fixed.as_slice(), bucket.as_slice());
+    let mut drawn = Vec::new();
+    while let Some(idx) = fixed.draw() {
+        drawn.push(idx);
+    }
+    assert_eq!(drawn, bucket.sorted_indices());
+    assert!(fixed.is_empty());
+    fixed.reset_noise(1.0);
+    assert_eq!(fixed.remaining(), 5);
+}
+
+#[test]
+fn draw_with_probability_is_the_conditional_softmax() {
+    let scores = [0.0f64, 1.0, 2.0];
+    let mut bucket = GumbelTopBucket::new_with_noise_slice(&scores, &[0.0; 3]);
+    // without a single temperature there is no closed form.
+    assert!(bucket.draw_with_probability().unwrap().1.is_nan());
+
+    let mut bucket = GumbelTopBucket::new(&scores, 1.0);
+    let mut remaining: Vec<usize> = vec![0, 1, 2];
+    while let Some((idx, probability)) = bucket.draw_with_probability() {
+        let total: f64 = remaining.iter().map(|&j| scores[j].exp()).sum();
+        assert!((probability - scores[idx].exp() / total).abs() < 1e-12);
+        remaining.retain(|&j| j != idx);
+    }
+    assert!(remaining.is_empty());
+}
+
+#[test]
+fn draw_with_probability_covers_the_scores_a_bucket_did_not_keep() {
+    let scores = [0.0f64, 1.0, 2.0, 3.0];
+    let mut rng = StdRng::seed_from_u64(97);
+    let mut single = GumbelTopBucket::new_single_with_rng(&scores, 1.0, &mut rng);
+    let (idx, probability) = single.draw_with_probability().unwrap();
+    assert!((probability - single.probability(idx).unwrap()).abs() < 1e-12);
+
+    let mut top_k = GumbelTopBucket::new_top_k_with_rng(&scores, 1.0, 2, &mut rng);
+    let (first, probability) = top_k.draw_with_probability().unwrap();
+    assert!((probability - top_k.probability(first).unwrap()).abs() < 1e-12);
+    let (second, probability) = top_k.draw_with_probability().unwrap();
+    let total: f64 = (0..4)
+        .filter(|&j| j != first)
+        .map(|j| scores[j].exp())
+        .sum();
+    assert!((probability - scores[second].exp() / total).abs() < 1e-12);
+}
+
+#[test]
+fn sort_orders_signed_zeros_and_non_finite_scores() {
+    let scores = [
+        0.0,
+        -0.0,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        f64::NAN,
+        1.0,
+        -1.0,
+        -0.0,
+    ];
+    let mut bucket = GumbelTopBucket::new_with_noise_slice(&scores, &[0.0; 8]);
+    assert_eq!(bucket.draw_n(8), vec![2, 5, 0, 1, 7, 6, 3, 4]);
+    let scores = scores.map(|score| score as f32);
+    let mut bucket = GumbelTopBucket::new_with_noise_slice(&scores, &[0.0; 8]);
+    assert_eq!(bucket.draw_n(8), vec![2, 5, 0, 1, 7, 6, 3, 4]);
+}
+
+#[test]
+fn weighted_permutation_matches_a_drained_bucket() {
+    let scores = [0.1, 0.4, 0.2, 0.9, 0.5];
+    let permutation =
+        gumbel_top_bucket::weighted_permutation(&scores, 1.0, &mut StdRng::seed_from_u64(100));
+    let bucket = GumbelTopBucket::new_with_rng(&scores, 1.0, &mut StdRng::seed_from_u64(100));
+    assert_eq!(permutation, bucket.into_iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn same_seed_gives_bit_identical_noisy_scores() {
+    let scores = [0.1, 0.4, 0.2, 0.9, 0.5, 0.3];
+    let a = GumbelTopBucket::new_with_rng(&scores, 1.0, &mut StdRng::seed_from_u64(1));
+    let b = GumbelTopBucket::new_with_rng(&scores, 1.0, &mut StdRng::seed_from_u64(1));
+    let bits = |bucket: &GumbelTopBucket| -> Vec<(usize, u64)> {
+        bucket
+            .as_slice()
+            .iter()
+            .map(|&(idx, noisy_score)| (idx, noisy_score.to_bits()))
+            .collect()
+    };
+    assert_eq!(bits(&a), bits(&b));
+}
+
+#[test]
+fn merge_renumbers_and_interleaves_the_other_bucket() {
+    let mut bucket = GumbelTopBucket::new_with_noise_slice(&[1.0, 3.0, 5.0], &[0.0; 3]);
+    let mut other = GumbelTopBucket::new_with_noise_slice(&[2.0, 6.0, 4.0], &[0.0; 3]);
+    // drawn from `other` before the merge, so it is not brought over.
+    assert_eq!(other.draw(), Some(1));
+    bucket.merge(other);
+    assert_eq!(bucket.original_len(), 6);
+    assert_eq!(bucket.original_score(3), Some(2.0));
+    assert_eq!(bucket.original_score(5), Some(4.0));
+    assert_eq!(bucket.sorted_indices(), vec![2, 5, 1, 3, 0]);
+
+    let mut rng = StdRng::seed_from_u64(46);
+    let mut bucket: GumbelTopBucket = GumbelTopBucket::new_with_rng(&[0.1, 0.2], 1.0, &mut rng);
+    bucket.merge(GumbelTopBucket::new_with_rng(&[0.3], 1.0, &mut rng));
+    assert_eq!(bucket.temperature(), 1.0);
+    bucket.merge(GumbelTopBucket::new_with_rng(&[0.4], 2.0, &mut rng));
+    assert!(bucket.temperature().is_nan());
+    assert_eq!(bucket.remaining(), 4);
+}
+
+#[test]
+fn adjust_score_moves_the_index_in_the_draw_order() {
+    let mut bucket = GumbelTopBucket::new_with_noise_slice(&[1.0, 2.0, 3.0, 4.0], &[0.0; 4]);
+    assert_eq!(bucket.draw(), Some(3));
+    assert!(bucket.adjust_score(0, 1.5));
+    assert_eq!(bucket.sorted_indices(), vec![2, 0, 1]);
+    assert!(bucket.adjust_score(2, -2.5));
+    assert_eq!(bucket.sorted_indices(), vec![0, 1, 2]);
+    // already drawn, and out of range.
+    assert!(!bucket.adjust_score(3, 10.0));
+    assert!(!bucket.adjust_score(4, 10.0));
+    assert_eq!(bucket.sorted_indices(), vec![0, 1, 2]);
+}
+
+#[test]
+fn top_k_keeps_the_k_first_draws_of_a_full_bucket() {
+    let scores: Vec<f64> = (0..50).map(|i| (i % 7) as f64 / 7.0).collect();
+    let full = GumbelTopBucket::new_with_rng(&scores, 1.0, &mut StdRng::seed_from_u64(16));
+    let top = GumbelTopBucket::new_top_k_with_rng(&scores, 1.0, 5, &mut StdRng::seed_from_u64(16));
+    assert_eq!(top.remaining(), 5);
+    assert_eq!(top.sorted_indices(), full.sorted_indices()[..5]);
+    let all = GumbelTopBucket::new_top_k_with_rng(&scores, 1.0, 80, &mut StdRng::seed_from_u64(16));
+    assert_eq!(all.remaining(), 50);
+    assert_eq!(all.sorted_indices(), full.sorted_indices());
+}
+
+#[test]
+fn removed_index_is_never_drawn() {
+    let mut bucket = GumbelTopBucket::new_with_noise_slice(&[1.0, 2.0, 3.0, 4.0], &[0.0; 4]);
+    assert_eq!(bucket.draw(), Some(3));
+    assert!(bucket.remove_index(1));
+    assert_eq!(bucket.remaining(), 2);
+    assert_eq!(bucket.sorted_indices(), vec![2, 0]);
+    // already removed, already drawn, and out of range.
+    assert!(!bucket.remove_index(1));
+    assert!(!bucket.remove_index(3));
+    assert!(!bucket.remove_index(4));
+    for _ in 0..20 {
+        bucket.reset_noise(1.0);
+        let mut drawn: Vec<usize> = bucket.drain().collect();
+        drawn.sort_unstable();
+        assert_eq!(drawn, vec![0, 2, 3]);
+    }
+}
+
+#[test]
+fn draw_filtered_skips_rejected_indices_without_removing_them() {
+    let scores = [0.1, 0.4, 0.2, 0.9, 0.5, 0.3];
+    let mut bucket = GumbelTopBucket::new_with_rng(&scores, 1.0, &mut StdRng::seed_from_u64(29));
+    let order = bucket.sorted_indices();
+    let first_even = *order.iter().find(|&&idx| idx % 2 == 0).unwrap();
+    assert_eq!(bucket.draw_filtered(|idx| idx % 2 == 0), Some(first_even));
+    let rest: Vec<usize> = order.into_iter().filter(|&idx| idx != first_even).collect();
+    assert_eq!(bucket.sorted_indices(), rest);
+    assert_eq!(bucket.draw_filtered(|idx| idx > 10), None);
+    assert_eq!(bucket.sorted_indices(), rest);
+}
+
+#[test]
+fn draw_until_stops_before_the_rejected_index() {
+    let probabilities = [0.1, 0.4, 0.2, 0.3];
+    let mut bucket =
+        GumbelTopBucket::new_with_rng(&probabilities, 1.0, &mut StdRng::seed_from_u64(45));
+    let order = bucket.sorted_indices();
+    let drawn = bucket.draw_until(|_, cumulative| cumulative < 0.5);
+    let drawn: Vec<usize> = drawn.into_iter().map(|(idx, _)| idx).collect();
+    // every drawn prefix but the full one sums to less than 0.5.
+    let mut cumulative = 0.0;
+    let expected: Vec<usize> = order
+        .iter()
+        .copied()
+        .take_while(|&idx| {
+            let keep = cumulative < 0.5;
+            cumulative += probabilities[idx];
+            keep
+        })
+        .collect();
+    assert!(!expected.is_empty());
+    assert_eq!(drawn, expected);
+    assert_eq!(bucket.sorted_indices(), order[expected.len()..]);
+}
+
+#[test]
+fn retain_keeps_the_order_of_the_kept_indices() {
+    let scores = [0.1, 0.4, 0.2, 0.9, 0.5, 0.3];
+    let mut bucket = GumbelTopBucket::new_with_rng(&scores, 1.0, &mut StdRng::seed_from_u64(58));
+    let first = bucket.draw().unwrap();
+    let order = bucket.sorted_indices();
+    bucket.retain(|idx, _| idx % 2 == 1);
+    let kept: Vec<usize> = order.into_iter().filter(|idx| idx % 2 == 1).collect();
+    assert_eq!(bucket.sorted_indices(), kept);
+    bucket.reset_noise(1.0);
+    let mut drawn: Vec<usize> = bucket.drain().collect();
+    drawn.sort_unstable();
+    let mut expected = kept;
+    expected.push(first);
+    expected.sort_unstable();
+    assert_eq!(drawn, expected);
+}
+
+#[test]
+fn draw_cycling_draws_every_index_once_per_epoch() {
+    let scores = [0.1, 0.4, 0.2, 0.9];
+    let mut rng = StdRng::seed_from_u64(68);
+    let mut bucket = GumbelTopBucket::new_with_rng(&scores, 1.0, &mut rng);
+    let order = bucket.sorted_indices();
+    let first_epoch: Vec<usize> = (0..4)
+        .map(|_| bucket.draw_cycling_with_rng(&mut rng))
+        .collect();
+    assert_eq!(first_epoch, order);
+    assert!(bucket.is_empty());
+    let mut second_epoch: Vec<usize> = (0..4)
+        .map(|_| bucket.draw_cycling_with_rng(&mut rng))
+        .collect();
+    assert_eq!(bucket.remaining(), 0);
+    second_epoch.sort_unstable();
+    assert_eq!(second_epoch, vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn draw_top_k_above_leaves_skipped_scores_in_order() {
+    let scores = [0.1, 0.4, 0.2, 0.9, 0.5, 0.3, 0.8];
+    let mut bucket = GumbelTopBucket::new_with_rng(&scores, 1.0, &mut StdRng::seed_from_u64(72));
+    let order = bucket.sorted_indices();
+    let above: Vec<usize> = order
+        .iter()
+        .copied()
+        .filter(|&idx| scores[idx] > 0.35)
+        .collect();
+    assert_eq!(bucket.draw_top_k_above(2, 0.35), above[..2]);
+    let rest: Vec<usize> = order
+        .into_iter()
+        .filter(|idx| !above[..2].contains(idx))
+        .collect();
+    assert_eq!(bucket.sorted_indices(), rest);
+    // fewer than k left above the threshold.
+    assert_eq!(bucket.draw_top_k_above(10, 0.35), above[2..]);
+    assert!(bucket
+        .as_slice()
+        .iter()
+        .all(|&(idx, _)| scores[idx] <= 0.35));
+    assert_eq!(bucket.remaining(), 3);
+}
+
+#[test]
+fn pushed_scores_get_the_next_index() {
+    let mut bucket = GumbelTopBucket::new_with_noise_slice(&[1.0, 2.0], &[0.0; 2]);
+    assert_eq!(bucket.draw(), Some(1));
+    let mut rng = StdRng::seed_from_u64(33);
+    assert_eq!(bucket.push_with_rng(f64::INFINITY, 1.0, &mut rng), 2);
+    assert_eq!(bucket.push_with_rng(f64::NEG_INFINITY, 1.0, &mut rng), 3);
+    assert_eq!(bucket.original_len(), 4);
+    assert_eq!(bucket.original_score(2), Some(f64::INFINITY));
+    assert_eq!(bucket.sorted_indices(), vec![2, 0, 3]);
+}
+
+#[test]
+fn sample_indices_matches_the_first_draws_of_a_bucket() {
+    let scores: Vec<f64> = (0..30).map(|i| (i % 5) as f64).collect();
+    let bucket = GumbelTopBucket::new_with_rng(&scores, 1.0, &mut StdRng::seed_from_u64(43));
+    let sampled =
+        gumbel_top_bucket::sample_indices(&scores, 1.0, 4, &mut StdRng::seed_from_u64(43));
+    assert_eq!(sampled, bucket.sorted_indices()[..4]);
+    let all = gumbel_top_bucket::sample_indices(&scores, 1.0, 100, &mut StdRng::seed_from_u64(43));
+    assert_eq!(all, bucket.sorted_indices());
+}
+
+#[test]
+fn new_single_holds_the_first_draw_of_new() {
+    let scores = [0.1, 0.4, 0.2, 0.9, 0.5];
+    for seed in 0..20 {
+        let full = GumbelTopBucket::new_with_rng(&scores, 1.0, &mut StdRng::seed_from_u64(seed));
+        let mut single =
+            GumbelTopBucket::new_single_with_rng(&scores, 1.0, &mut StdRng::seed_from_u64(seed));
+        assert_eq!(single.remaining(), 1);
+        assert_eq!(single.draw(), full.peek());
+        assert_eq!(single.draw(), None);
+    }
+}
+
+#[test]
+fn gumbel_argmax_is_the_first_draw() {
+    let scores = [0.1, 0.4, 0.2, 0.9, 0.5];
+    for seed in 0..20 {
+        let bucket = GumbelTopBucket::new_with_rng(&scores, 1.0, &mut StdRng::seed_from_u64(seed));
+        let argmax =
+            gumbel_top_bucket::gumbel_argmax(&scores, 1.0, &mut StdRng::seed_from_u64(seed));
+        assert_eq!(argmax, bucket.peek());
+    }
+    let empty: [f64; 0] = [];
+    assert_eq!(
+        gumbel_top_bucket::gumbel_argmax(&empty, 1.0, &mut StdRng::seed_from_u64(0)),
+        None
+    );
+}
+
+#[test]
+fn update_scores_keeps_drawn_indices_drawn() {
+    let mut rng = StdRng::seed_from_u64(71);
+    let mut bucket = GumbelTopBucket::new_with_rng(&[0.1, 0.4, 0.2, 0.9], 1.0, &mut rng);
+    let drawn = bucket.draw_n(2);
+    let favourite = (0..4).find(|idx| !drawn.contains(idx)).unwrap();
+    let mut new_scores = [0.0; 4];
+    new_scores[favourite] = 1e6;
+    bucket.update_scores_with_rng(&new_scores, 0.5, &mut rng);
+    assert_eq!(bucket.original_score(favourite), Some(1e6));
+    assert_eq!(bucket.temperature(), 0.5);
+    assert_eq!(bucket.remaining(), 2);
+    let remaining = bucket.sorted_indices();
+    assert!(remaining.iter().all(|idx| !drawn.contains(idx)));
+    assert_eq!(remaining[0], favourite);
+    assert!(std::panic::catch_unwind(move || {
+        bucket.update_scores_with_rng(&[1.0], 1.0, &mut StdRng::seed_from_u64(0))
+    })
+    .is_err());
+}
+
+#[test]
+fn reservoir_keeps_the_top_k_of_the_logarithms() {
+    let scores = [0.3, 0.05, 0.9, 0.2, 0.6, 0.1, 0.45, 0.7];
+    let logits: Vec<f64> = scores.iter().map(|score: &f64| score.ln()).collect();
+    for seed in 0..20 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut reservoir = GumbelReservoir::new(3);
+        for &score in &scores {
+            reservoir.push_with_rng(score, &mut rng);
+        }
+        let kept: Vec<usize> = reservoir
+            .into_sorted()
+            .into_iter()
+            .map(|(idx, _)| idx)
+            .collect();
+        let bucket =
+            GumbelTopBucket::new_top_k_with_rng(&logits, 1.0, 3, &mut StdRng::seed_from_u64(seed));
+        assert_eq!(kept, bucket.sorted_indices());
+    }
+
+    let mut rng = StdRng::seed_from_u64(62);
+    let mut reservoir = GumbelReservoir::new(0);
+    for &score in &scores {
+        reservoir.push_with_rng(score, &mut rng);
+    }
+    assert!(reservoir.is_empty());
+    assert!(reservoir.into_sorted().is_empty());
+}
+
+#[test]
+fn builder_top_k_matches_new_top_k() {
+    let scores = [0.3, 0.05, 0.9, 0.2, 0.6, 0.1, 0.45, 0.7];
+    for seed in 0..20 {
+        let built = GumbelBucketBuilder::new()
+            .temperature(0.5)
+            .top_k(3)
+            .rng(StdRng::seed_from_u64(seed))
+            .build(&scores);
+        let bucket =
+            GumbelTopBucket::new_top_k_with_rng(&scores, 0.5, 3, &mut StdRng::seed_from_u64(seed));
+        assert_eq!(built.remaining(), 3);
+        assert_eq!(built.sorted_indices(), bucket.sorted_indices());
+    }
+}
+
+#[test]
+fn new_normalized_rejects_non_finite_scores() {
+    let bucket: GumbelTopBucket =
+        GumbelTopBucket::new_normalized_with_rng(&[1.0, 3.0], 1.0, &mut StdRng::seed_from_u64(50));
+    let total: f64 = (0..2).map(|idx| bucket.original_score(idx).unwrap()).sum();
+    assert!((total - 1.0).abs() < 1e-12);
+
+    for bad in [f64::INFINITY, f64::NEG_INFINITY, f64::NAN] {
+        let result = std::panic::catch_unwind(|| {
+            GumbelTopBucket::<f64>::new_normalized_with_rng(
+                &[1.0, bad],
+                1.0,
+                &mut StdRng::seed_from_u64(50),
+            )
+        });
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,28 @@
+#![cfg(feature = "std")]
+
+use gumbel_top_bucket::GumbelTopBucket;
+use proptest::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+proptest! {
+    #[test]
+    fn drain_is_a_permutation_in_descending_order(
+        scores in prop::collection::vec(-10.0f64..10.0, 1..200),
+        temperature in 0.01f64..10.0,
+        seed: u64,
+    ) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut bucket = GumbelTopBucket::new_with_rng(&scores, temperature, &mut rng);
+        let drawn: Vec<(usize, f64)> = bucket.drain_with_scores().collect();
+
+        for pair in drawn.windows(2) {
+            prop_assert!(pair[0].1 >= pair[1].1);
+        }
+        let mut indices: Vec<usize> = drawn.iter().map(|&(idx, _)| idx).collect();
+        indices.sort_unstable();
+        prop_assert_eq!(indices, (0..scores.len()).collect::<Vec<_>>());
+        prop_assert!(bucket.is_empty());
+        prop_assert_eq!(bucket.draw(), None);
+    }
+}